@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN stalled_at DATETIME;
+ALTER TABLE tasks ADD COLUMN retry_count INTEGER NOT NULL DEFAULT 0;
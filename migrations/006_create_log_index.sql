@@ -0,0 +1,16 @@
+-- 任务日志全文索引，每行日志一条记录
+CREATE VIRTUAL TABLE log_index USING fts5(
+    content,
+    task_id UNINDEXED,
+    line_number UNINDEXED
+);
+
+-- 每个任务日志的索引进度
+CREATE TABLE log_index_state (
+    task_id TEXT PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    byte_offset INTEGER NOT NULL,
+    line_count INTEGER NOT NULL,
+    complete BOOLEAN NOT NULL DEFAULT 0,
+    updated_at DATETIME NOT NULL
+);
@@ -0,0 +1,21 @@
+-- 从任务日志解析出的训练指标，每个任务每次迭代每个指标一条记录
+CREATE TABLE metrics (
+    task_id TEXT NOT NULL,
+    iteration INTEGER NOT NULL,
+    key TEXT NOT NULL,
+    value REAL NOT NULL,
+    wall_time DATETIME NOT NULL,
+    PRIMARY KEY (task_id, key, iteration)
+);
+
+CREATE INDEX idx_metrics_task_iteration ON metrics(task_id, iteration);
+
+-- 每个任务日志的指标导入进度
+CREATE TABLE metrics_ingest_state (
+    task_id TEXT PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    byte_offset INTEGER NOT NULL,
+    current_iteration INTEGER NOT NULL,
+    complete BOOLEAN NOT NULL DEFAULT 0,
+    updated_at DATETIME NOT NULL
+);
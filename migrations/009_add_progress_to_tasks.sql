@@ -0,0 +1,5 @@
+ALTER TABLE tasks ADD COLUMN current_iteration INTEGER;
+ALTER TABLE tasks ADD COLUMN total_iterations INTEGER;
+ALTER TABLE tasks ADD COLUMN progress_pct REAL;
+ALTER TABLE tasks ADD COLUMN steps_per_sec REAL;
+ALTER TABLE tasks ADD COLUMN eta_seconds REAL;
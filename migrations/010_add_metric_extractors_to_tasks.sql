@@ -0,0 +1 @@
+ALTER TABLE tasks ADD COLUMN metric_extractors TEXT;
@@ -0,0 +1,2 @@
+ALTER TABLE tasks ADD COLUMN failure_category TEXT;
+ALTER TABLE tasks ADD COLUMN failure_excerpt TEXT;
@@ -0,0 +1,20 @@
+-- 从任务检查点提升而来的模型版本，文件保存在模型仓库目录中
+CREATE TABLE models (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    task_id TEXT NOT NULL,
+    checkpoint TEXT NOT NULL,
+    iteration INTEGER,
+    command TEXT NOT NULL,
+    code_hash TEXT,
+    notes TEXT,
+    metrics TEXT NOT NULL,
+    file_path TEXT NOT NULL,
+    size INTEGER NOT NULL,
+    sha256 TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    UNIQUE (name, version)
+);
+
+CREATE INDEX idx_models_task_id ON models(task_id);
@@ -0,0 +1,4 @@
+-- 每隔多少个检查点自动评估一次；评估任务记录其来源任务和检查点迭代
+ALTER TABLE tasks ADD COLUMN eval_every INTEGER;
+ALTER TABLE tasks ADD COLUMN eval_source_task_id TEXT;
+ALTER TABLE tasks ADD COLUMN eval_iteration INTEGER;
@@ -0,0 +1,2 @@
+-- 任务启动时工作目录代码的哈希，提升为模型时记录
+ALTER TABLE tasks ADD COLUMN code_hash TEXT;
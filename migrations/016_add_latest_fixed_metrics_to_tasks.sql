@@ -0,0 +1,2 @@
+-- 摄取日志时记录的固定指标最新值，保留日志中的原始文本（如 ETA）
+ALTER TABLE tasks ADD COLUMN latest_fixed_metrics TEXT;
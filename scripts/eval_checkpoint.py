@@ -0,0 +1,119 @@
+"""Evaluates a checkpoint for the IsaacLab training manager.
+
+Runs an Isaac Lab play script (rsl_rl's by default) for a number of episodes and prints
+the results on `[EVAL] key: value` lines, which the manager records as the `Eval/`
+metrics of the evaluated task:
+
+    [EVAL] episode_return: 41.7
+    [EVAL] episode_length: 812.0
+    [EVAL] success_rate: 0.93
+    [EVAL] episodes: 100
+
+An episode counts as a success when it runs into the time limit instead of being
+terminated early (e.g. by a fall). All arguments except the ones below are passed on
+to the play script:
+
+    python eval_checkpoint.py --task Isaac-Velocity-Flat-Anymal-D-v0 \\
+        --checkpoint logs/rsl_rl/anymal_d_flat/2026-03-02_10-15-42/model_500.pt \\
+        --num_envs 64 --headless
+"""
+
+import argparse
+import os
+import runpy
+import sys
+
+DEFAULT_PLAY_SCRIPT = "scripts/reinforcement_learning/rsl_rl/play.py"
+
+
+class Episodes:
+    """Sums up the rewards of every environment until enough episodes have finished."""
+
+    def __init__(self, count):
+        self.count = count
+        self.returns = None
+        self.lengths = None
+        self.finished_returns = []
+        self.finished_lengths = []
+        self.successes = 0
+        self.reported = False
+
+    def record(self, reward, terminated, truncated):
+        reward = reward.flatten().float()
+        if self.returns is None:
+            self.returns = reward.new_zeros(reward.shape)
+            self.lengths = reward.new_zeros(reward.shape)
+        self.returns += reward
+        self.lengths += 1
+        terminated = terminated.flatten().bool()
+        truncated = truncated.flatten().bool()
+        done = (terminated | truncated).nonzero().flatten()
+        if len(done) > 0:
+            self.finished_returns.extend(self.returns[done].tolist())
+            self.finished_lengths.extend(self.lengths[done].tolist())
+            self.successes += int((truncated & ~terminated)[done].sum())
+            self.returns[done] = 0
+            self.lengths[done] = 0
+        return len(self.finished_returns) >= self.count
+
+    def report(self):
+        if self.reported:
+            return
+        self.reported = True
+        episodes = len(self.finished_returns)
+        if episodes == 0:
+            print("[eval_checkpoint] No episode finished, no results to report.", flush=True)
+            return
+        print(f"[EVAL] episode_return: {sum(self.finished_returns) / episodes}")
+        print(f"[EVAL] episode_length: {sum(self.finished_lengths) / episodes}")
+        print(f"[EVAL] success_rate: {self.successes / episodes}")
+        print(f"[EVAL] episodes: {episodes}", flush=True)
+
+
+def count_episodes(gym, episodes):
+    """Makes every environment created with `gym.make` report its steps to `episodes`."""
+    make = gym.make
+
+    def counting_make(*args, **kwargs):
+        env = make(*args, **kwargs)
+        step = env.step
+
+        def counting_step(action):
+            result = step(action)
+            _, reward, terminated, truncated, _ = result
+            if episodes.record(reward, terminated, truncated):
+                episodes.report()
+                # Play scripts run until the app is closed; shutting Isaac Sim down
+                # cleanly can take minutes, so leave right away.
+                os._exit(0)
+            return result
+
+        env.step = counting_step
+        return env
+
+    gym.make = counting_make
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("--episodes", type=int, default=100, help="Episodes to evaluate.")
+    parser.add_argument("--play_script", default=DEFAULT_PLAY_SCRIPT, help="Play script to run the policy with.")
+    args, play_args = parser.parse_known_args()
+    if args.episodes < 1:
+        parser.error("--episodes must be at least 1")
+
+    import gymnasium as gym
+
+    episodes = Episodes(args.episodes)
+    count_episodes(gym, episodes)
+
+    sys.argv = [args.play_script, *play_args]
+    try:
+        runpy.run_path(args.play_script, run_name="__main__")
+    finally:
+        # The play script ended on its own, e.g. after recording a video.
+        episodes.report()
+
+
+if __name__ == "__main__":
+    main()
@@ -1,23 +1,61 @@
 use anyhow::{Context, Result};
 use clap::Parser;
+use futures::TryStreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::header::{HeaderMap, HeaderValue};
 use reqwest::Client;
 use serde::Deserialize;
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
-use std::io::{Seek, Write};
+use std::io::{Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs as tokio_fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
 use walkdir::WalkDir;
 use zip::ZipArchive;
 
+/// Files at least this large are uploaded in chunks, which survive interruptions.
+const CHUNKED_UPLOAD_THRESHOLD: u64 = 32 * 1024 * 1024;
+/// How many times an interrupted chunked upload is resumed before giving up.
+const MAX_UPLOAD_RETRIES: u32 = 5;
+
 #[derive(Deserialize, Debug)]
 struct SyncConfigResponse {
     default_excludes: Vec<String>,
 }
 
+/// A file that is uploaded in chunks.
+struct LargeFile<'a> {
+    local_path: PathBuf,
+    relative_path: &'a str,
+    sha256: &'a str,
+    size: u64,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChunkedUploadStatus {
+    upload_id: String,
+    chunk_size: u64,
+    chunk_count: u64,
+    received: Vec<u64>,
+}
+
+/// The server refused to commit an upload, e.g. because the assembled file did not
+/// match its hash. It has dropped the upload, so resuming would not help.
+#[derive(Debug)]
+struct CommitRejected(String);
+
+impl std::fmt::Display for CommitRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server rejected the upload: {}", self.0)
+    }
+}
+
+impl std::error::Error for CommitRejected {}
+
 /// A client to synchronize and download files from the IsaacLab Manager server.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -61,6 +99,14 @@ enum Commands {
         /// The remote directory on the server to upload to
         #[arg(long)]
         remote_dir: Option<String>,
+
+        /// Also delete files on the server that no longer exist locally
+        #[arg(long)]
+        delete: bool,
+
+        /// Delete without asking for confirmation
+        #[arg(short, long, requires = "delete")]
+        yes: bool,
     },
 }
 
@@ -79,13 +125,20 @@ async fn main() -> Result<()> {
     match args.command {
         Commands::Sync { dir, remote_dir } => handle_sync(&client, &args.server, &dir, remote_dir.as_ref()).await?,
         Commands::Download { remote_path, local_path } => handle_download(&client, &args.server, &remote_path, &local_path).await?,
-        Commands::Upload { dir, remote_dir } => handle_upload(&client, &args.server, &dir, remote_dir.as_ref()).await?,
+        Commands::Upload { dir, remote_dir, delete, yes } => handle_upload(&client, &args.server, &dir, remote_dir.as_ref(), delete, yes).await?,
     }
 
     Ok(())
 }
 
-async fn handle_upload(client: &Client, server: &str, dir: &Path, remote_dir: Option<&String>) -> Result<()> {
+async fn handle_upload(
+    client: &Client,
+    server: &str,
+    dir: &Path,
+    remote_dir: Option<&String>,
+    delete: bool,
+    yes: bool,
+) -> Result<()> {
     if let Some(remote_dir) = remote_dir {
         println!("Remote Directory: {}", remote_dir);
     }
@@ -136,7 +189,7 @@ async fn handle_upload(client: &Client, server: &str, dir: &Path, remote_dir: Op
     );
     for (relative_path, local_hash) in &local_manifest {
         let should_upload = server_manifest.get(relative_path)
-            .map_or(true, |server_hash| server_hash != local_hash);
+            .is_none_or(|server_hash| server_hash != local_hash);
 
         if should_upload {
             files_to_upload.push(relative_path.clone());
@@ -158,45 +211,219 @@ async fn handle_upload(client: &Client, server: &str, dir: &Path, remote_dir: Op
         }
         println!();
 
-        let mut form = reqwest::multipart::Form::new();
+        // Large files go up in resumable chunks, one at a time; the rest in one request.
+        let mut small_files = Vec::new();
         for relative_path in &files_to_upload {
-            let local_path = dir.join(relative_path);
-            let file_contents = tokio_fs::read(&local_path).await?;
-            let part = reqwest::multipart::Part::bytes(file_contents)
-                .file_name(relative_path.clone());
-            form = form.part("files", part);
+            let size = tokio_fs::metadata(dir.join(relative_path)).await?.len();
+            if size >= CHUNKED_UPLOAD_THRESHOLD {
+                let file = LargeFile {
+                    local_path: dir.join(relative_path),
+                    relative_path,
+                    sha256: &local_manifest[relative_path],
+                    size,
+                };
+                upload_chunked(client, server, remote_dir, &file).await?;
+            } else {
+                small_files.push((relative_path, size));
+            }
         }
 
-        let upload_url = format!("{}/api/sync", server);
+        if !small_files.is_empty() {
+            let pb_upload = ProgressBar::new(0);
+            pb_upload.set_style(ProgressStyle::default_bar()
+                .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+                .progress_chars("=> ")
+            );
+            pb_upload.set_message(format!("Uploading {} files...", small_files.len()));
+
+            // Files are streamed from disk as the request is sent, each opened only when
+            // its turn comes, and carry their hash so the server can verify them.
+            let mut form = reqwest::multipart::Form::new();
+            for (relative_path, size) in small_files {
+                let local_path = dir.join(relative_path);
+                pb_upload.inc_length(size);
+                let pb = pb_upload.clone();
+                let stream = futures::stream::once(tokio_fs::File::open(local_path))
+                    .map_ok(ReaderStream::new)
+                    .try_flatten()
+                    .inspect_ok(move |chunk| pb.inc(chunk.len() as u64));
+                let mut headers = HeaderMap::new();
+                headers.insert("x-sha256", HeaderValue::from_str(&local_manifest[relative_path])?);
+                let part = reqwest::multipart::Part::stream_with_length(reqwest::Body::wrap_stream(stream), size)
+                    .file_name(relative_path.clone())
+                    .headers(headers);
+                form = form.part("files", part);
+            }
+
+            let upload_url = format!("{}/api/sync", server);
 
-        let pb_upload = ProgressBar::new(1);
-        pb_upload.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {msg}")?
-            .progress_chars("=> ")
-        );
-        pb_upload.set_message(format!("Uploading {} files...", files_to_upload.len()));
-        pb_upload.enable_steady_tick(Duration::from_millis(120));
+            let mut request_builder = client.post(&upload_url);
+            if let Some(rd) = remote_dir {
+                request_builder = request_builder.query(&[("remote_path", rd)]);
+            }
+
+            let response = request_builder
+                .multipart(form)
+                .send()
+                .await?
+                .error_for_status()?;
+
+            let response_json: serde_json::Value = response.json().await?;
+            let message = response_json["message"].as_str().unwrap_or("Upload complete.");
 
-        let mut request_builder = client.post(&upload_url);
-        if let Some(rd) = remote_dir {
-            request_builder = request_builder.query(&[("remote_path", rd)]);
+            pb_upload.finish_with_message(format!("✔ {}", message));
         }
+    }
+
+    // 5. Delete files that are gone locally. Both manifests leave out excluded files,
+    // so those are never deleted.
+    if delete {
+        let mut files_to_delete: Vec<&String> = server_manifest
+            .keys()
+            .filter(|relative_path| !local_manifest.contains_key(*relative_path))
+            .collect();
+        files_to_delete.sort();
+
+        if files_to_delete.is_empty() {
+            println!("\nNo files to delete on the server.");
+        } else {
+            println!(
+                "\nFound {} files on the server that no longer exist locally:",
+                files_to_delete.len()
+            );
+            for file in &files_to_delete {
+                println!("  - {}", file);
+            }
 
-        let response = request_builder
-            .multipart(form)
+            if yes || confirm(&format!("Delete these {} files from the server?", files_to_delete.len()))? {
+                let delete_url = format!("{}/api/sync/delete", server);
+                let mut request_builder = client.post(&delete_url);
+                if let Some(rd) = remote_dir {
+                    request_builder = request_builder.query(&[("remote_path", rd)]);
+                }
+                let response_json: serde_json::Value = request_builder
+                    .json(&serde_json::json!({ "paths": files_to_delete }))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await?;
+                let message = response_json["message"].as_str().unwrap_or("Delete complete.");
+                println!("✔ {}", message);
+            } else {
+                println!("Skipped deleting files on the server.");
+            }
+        }
+    }
+
+    println!("\nSync to server complete!");
+    Ok(())
+}
+
+/// Uploads a large file in chunks. When the upload is interrupted it is resumed, up
+/// to `MAX_UPLOAD_RETRIES` times, sending only the chunks the server is missing; the
+/// server also recognizes the file when the client is run again.
+async fn upload_chunked(client: &Client, server: &str, remote_dir: Option<&String>, file: &LargeFile<'_>) -> Result<()> {
+    let relative_path = file.relative_path;
+    let pb = ProgressBar::new(file.size);
+    pb.set_style(ProgressStyle::default_bar()
+        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?
+        .progress_chars("=> "));
+    pb.set_message(format!("Uploading {} in chunks...", relative_path));
+
+    let mut attempt = 0;
+    loop {
+        match try_upload_chunked(client, server, remote_dir, file, &pb).await {
+            Ok(message) => {
+                pb.finish_with_message(format!("✔ {}", message));
+                return Ok(());
+            }
+            Err(e) if attempt < MAX_UPLOAD_RETRIES && !e.is::<CommitRejected>() => {
+                attempt += 1;
+                let delay = Duration::from_secs(2u64.pow(attempt));
+                pb.println(format!(
+                    "Upload of {} interrupted: {:#}. Resuming in {}s ({}/{})...",
+                    relative_path,
+                    e,
+                    delay.as_secs(),
+                    attempt,
+                    MAX_UPLOAD_RETRIES
+                ));
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => {
+                pb.abandon();
+                return Err(e.context(format!("Failed to upload {}", relative_path)));
+            }
+        }
+    }
+}
+
+async fn try_upload_chunked(
+    client: &Client,
+    server: &str,
+    remote_dir: Option<&String>,
+    file: &LargeFile<'_>,
+    pb: &ProgressBar,
+) -> Result<String> {
+    let size = file.size;
+    let mut request = client
+        .post(format!("{}/api/sync/uploads", server))
+        .json(&serde_json::json!({ "path": file.relative_path, "size": size, "sha256": file.sha256 }));
+    if let Some(rd) = remote_dir {
+        request = request.query(&[("remote_path", rd)]);
+    }
+    let status: ChunkedUploadStatus = request.send().await?.error_for_status()?.json().await?;
+
+    let received: HashSet<u64> = status.received.into_iter().collect();
+    let chunk_len = |index: u64| status.chunk_size.min(size - index * status.chunk_size);
+    pb.set_position(received.iter().map(|index| chunk_len(*index)).sum());
+
+    let mut local_file = tokio_fs::File::open(&file.local_path).await?;
+    for index in (0..status.chunk_count).filter(|index| !received.contains(index)) {
+        let mut chunk = vec![0; chunk_len(index) as usize];
+        local_file.seek(SeekFrom::Start(index * status.chunk_size)).await?;
+        local_file.read_exact(&mut chunk).await?;
+        let chunk_hash = format!("{:x}", Sha256::digest(&chunk));
+        client
+            .put(format!("{}/api/sync/uploads/{}/chunks/{}", server, status.upload_id, index))
+            .header("x-sha256", chunk_hash)
+            .body(chunk)
             .send()
             .await?
             .error_for_status()?;
+        pb.inc(chunk_len(index));
+    }
 
-        pb_upload.inc(1);
-        let response_json: serde_json::Value = response.json().await?;
-        let message = response_json["message"].as_str().unwrap_or("Upload complete.");
-
-        pb_upload.finish_with_message(format!("✔ {}", message));
+    let response = client
+        .post(format!("{}/api/sync/uploads/{}/commit", server, status.upload_id))
+        .send()
+        .await?;
+    if response.status() == reqwest::StatusCode::BAD_REQUEST {
+        let error: serde_json::Value = response.json().await.unwrap_or_default();
+        let message = error["error"].as_str().unwrap_or("bad request").to_string();
+        return Err(CommitRejected(message).into());
     }
+    let response_json: serde_json::Value = response.error_for_status()?.json().await?;
+    Ok(response_json["message"].as_str().unwrap_or("Upload complete.").to_string())
+}
 
-    println!("\nSync to server complete!");
-    Ok(())
+/// Asks a yes/no question on the terminal; anything but "y" or "yes" means no.
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Matches the server's exclude rules: patterns ending in `/`, like `logs/`, match
+/// directories.
+fn is_excluded(patterns: &[glob::Pattern], relative_path: &Path, is_dir: bool) -> bool {
+    let path = relative_path.to_string_lossy().replace('\\', "/");
+    patterns
+        .iter()
+        .any(|p| p.matches(&path) || (is_dir && p.matches(&format!("{}/", path))))
 }
 
 async fn get_local_manifest(
@@ -218,7 +445,7 @@ async fn get_local_manifest(
             if relative_path.as_os_str().is_empty() {
                 return true;
             }
-            !exclude_patterns.iter().any(|p| p.matches_path(relative_path))
+            !is_excluded(&exclude_patterns, relative_path, e.file_type().is_dir())
         });
 
         for entry in filtered_walker {
@@ -293,7 +520,7 @@ async fn handle_download(client: &Client, server: &str, remote_path: &str, local
             } else {
                 if let Some(p) = outpath.parent() {
                     if !p.exists() {
-                        std::fs::create_dir_all(&p)?;
+                        std::fs::create_dir_all(p)?;
                     }
                 }
                 let mut outfile = std::fs::File::create(&outpath)?;
@@ -380,7 +607,7 @@ async fn handle_sync(client: &Client, server: &str, dir: &Path, remote_dir: Opti
                 .progress_chars("=> "),
         );
 
-        for (_i, relative_path) in files_to_download.iter().enumerate() {
+        for relative_path in &files_to_download {
             pb_download.set_message(relative_path.clone());
             let download_url = format!("{}/api/sync/download/{}", server, relative_path);
 
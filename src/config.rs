@@ -5,9 +5,49 @@ use sqlx::{Row, SqlitePool};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::failure_classifier::FailureRule;
+use crate::metrics_parser::{DEFAULT_EXCLUDED_METRICS, DEFAULT_FIXED_METRICS};
+
+/// Evaluates checkpoints with the shipped wrapper around rsl_rl's play script.
+pub const DEFAULT_EVALUATION_TEMPLATE: &str =
+    "python {eval_script} --task {task} --checkpoint {checkpoint} --num_envs 64 --headless";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MetricsConfig {
     pub auto_refresh_interval_secs: u64,
+    /// Metrics of which only the latest value is shown, in addition to the ones the
+    /// parser of a log format treats as fixed.
+    pub fixed_metrics: Vec<String>,
+    /// Metrics that are not recorded at all. An entry drops every key containing it,
+    /// ignoring case.
+    pub excluded_metrics: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TensorboardConfig {
+    /// First port handed out to TensorBoard instances.
+    pub port_range_start: u16,
+    /// Last port handed out to TensorBoard instances, inclusive.
+    pub port_range_end: u16,
+    /// Seconds without a proxied request after which an instance is shut down.
+    pub idle_timeout_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryConfig {
+    /// Directory promoted checkpoints are stored in, as `<name>/<version>/<file>`.
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationConfig {
+    /// Command queued to evaluate a checkpoint of a task that asks for evaluations.
+    /// `{checkpoint}`, `{run_dir}` and `{iteration}` are replaced with the checkpoint's
+    /// path, its run directory and its iteration, `{task}` with the Isaac Lab task the
+    /// source task trains (its name if the command has no `--task`), and `{eval_script}`
+    /// with the path of the shipped evaluation wrapper. Empty disables automatic
+    /// evaluation.
+    pub command_template: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +63,9 @@ pub struct Config {
     pub sync: SyncConfig,
     pub tasks: TaskConfig,
     pub metrics: MetricsConfig,
+    pub tensorboard: TensorboardConfig,
+    pub registry: RegistryConfig,
+    pub evaluation: EvaluationConfig,
     pub files: FilesConfig,
 }
 
@@ -48,11 +91,23 @@ pub struct StorageConfig {
 pub struct SyncConfig {
     pub target_path: PathBuf,
     pub default_excludes: Vec<String>,
+    /// Largest file accepted by a chunked upload, in bytes.
+    pub max_upload_bytes: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskConfig {
     pub working_directory: PathBuf,
+    /// Seconds a running task may go without new log output or a new training
+    /// iteration before it is flagged as stalled. `0` disables stall detection.
+    pub stall_timeout_secs: u64,
+    /// Kill stalled tasks instead of only flagging them.
+    pub kill_on_stall: bool,
+    /// How many times a task killed for stalling is re-queued before it is marked as failed.
+    pub stall_max_retries: u32,
+    /// Failure signatures checked before the built-in ones when a failed task's log
+    /// is classified.
+    pub failure_rules: Vec<FailureRule>,
 }
 
 impl Default for Config {
@@ -82,12 +137,30 @@ impl Default for Config {
                     ".DS_Store".to_string(),
                     "target".to_string(),
                 ],
+                max_upload_bytes: 64 * 1024 * 1024 * 1024,
             },
             tasks: TaskConfig {
                 working_directory: PathBuf::from("./ecs-user-files"),
+                stall_timeout_secs: 1800,
+                kill_on_stall: false,
+                stall_max_retries: 1,
+                failure_rules: Vec::new(),
             },
             metrics: MetricsConfig {
                 auto_refresh_interval_secs: 30,
+                fixed_metrics: DEFAULT_FIXED_METRICS.iter().map(|key| key.to_string()).collect(),
+                excluded_metrics: DEFAULT_EXCLUDED_METRICS.iter().map(|key| key.to_string()).collect(),
+            },
+            tensorboard: TensorboardConfig {
+                port_range_start: 6100,
+                port_range_end: 6199,
+                idle_timeout_secs: 1800,
+            },
+            registry: RegistryConfig {
+                path: PathBuf::from("./registry"),
+            },
+            evaluation: EvaluationConfig {
+                command_template: DEFAULT_EVALUATION_TEMPLATE.to_string(),
             },
             files: FilesConfig {
                 ignore_patterns: vec![".*".to_string(), "下载".to_string(), "桌面".to_string(), "公共".to_string(), "模板".to_string(), "图片".to_string(), "音乐".to_string(), "视频".to_string()],
@@ -150,18 +223,71 @@ impl Config {
                     .remove("sync_default_excludes")
                     .and_then(|v| serde_json::from_str(&v).ok())
                     .unwrap_or(default_config.sync.default_excludes),
+                max_upload_bytes: db_config
+                    .remove("sync_max_upload_bytes")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.sync.max_upload_bytes),
             },
             tasks: TaskConfig {
                 working_directory: db_config
                     .remove("tasks_working_directory")
                     .map(PathBuf::from)
                     .unwrap_or(default_config.tasks.working_directory),
+                stall_timeout_secs: db_config
+                    .remove("tasks_stall_timeout_secs")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tasks.stall_timeout_secs),
+                kill_on_stall: db_config
+                    .remove("tasks_kill_on_stall")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tasks.kill_on_stall),
+                stall_max_retries: db_config
+                    .remove("tasks_stall_max_retries")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tasks.stall_max_retries),
+                failure_rules: db_config
+                    .remove("tasks_failure_rules")
+                    .and_then(|v| serde_json::from_str(&v).ok())
+                    .unwrap_or(default_config.tasks.failure_rules),
             },
             metrics: MetricsConfig {
                 auto_refresh_interval_secs: db_config
                     .remove("metrics_auto_refresh_interval_secs")
                     .and_then(|v| v.parse().ok())
                     .unwrap_or(default_config.metrics.auto_refresh_interval_secs),
+                fixed_metrics: db_config
+                    .remove("metrics_fixed_metrics")
+                    .and_then(|v| serde_json::from_str(&v).ok())
+                    .unwrap_or(default_config.metrics.fixed_metrics),
+                excluded_metrics: db_config
+                    .remove("metrics_excluded_metrics")
+                    .and_then(|v| serde_json::from_str(&v).ok())
+                    .unwrap_or(default_config.metrics.excluded_metrics),
+            },
+            tensorboard: TensorboardConfig {
+                port_range_start: db_config
+                    .remove("tensorboard_port_range_start")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tensorboard.port_range_start),
+                port_range_end: db_config
+                    .remove("tensorboard_port_range_end")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tensorboard.port_range_end),
+                idle_timeout_secs: db_config
+                    .remove("tensorboard_idle_timeout_secs")
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(default_config.tensorboard.idle_timeout_secs),
+            },
+            registry: RegistryConfig {
+                path: db_config
+                    .remove("registry_path")
+                    .map(PathBuf::from)
+                    .unwrap_or(default_config.registry.path),
+            },
+            evaluation: EvaluationConfig {
+                command_template: db_config
+                    .remove("evaluation_command_template")
+                    .unwrap_or(default_config.evaluation.command_template),
             },
             files: FilesConfig {
                 ignore_patterns: db_config
@@ -181,17 +307,30 @@ impl Config {
     pub async fn save_to_db(&self, db: &SqlitePool) -> Result<()> {
         let mut tx = db.begin().await?;
 
-        let mut kvs: Vec<(&str, String)> = Vec::new();
-        kvs.push(("server_host", self.server.host.clone()));
-        kvs.push(("server_port", self.server.port.to_string()));
+        let mut kvs: Vec<(&str, String)> = vec![
+            ("server_host", self.server.host.clone()),
+            ("server_port", self.server.port.to_string()),
+        ];
         kvs.push(("isaaclab_conda_path", self.isaaclab.conda_path.to_string_lossy().into_owned()));
         kvs.push(("isaaclab_default_conda_env", self.isaaclab.default_conda_env.clone()));
         kvs.push(("storage_output_path", self.storage.output_path.to_string_lossy().into_owned()));
         kvs.push(("sync_target_path", self.sync.target_path.to_string_lossy().into_owned()));
         let excludes_json = serde_json::to_string(&self.sync.default_excludes)?;
         kvs.push(("sync_default_excludes", excludes_json));
+        kvs.push(("sync_max_upload_bytes", self.sync.max_upload_bytes.to_string()));
         kvs.push(("tasks_working_directory", self.tasks.working_directory.to_string_lossy().into_owned()));
+        kvs.push(("tasks_stall_timeout_secs", self.tasks.stall_timeout_secs.to_string()));
+        kvs.push(("tasks_kill_on_stall", self.tasks.kill_on_stall.to_string()));
+        kvs.push(("tasks_stall_max_retries", self.tasks.stall_max_retries.to_string()));
+        kvs.push(("tasks_failure_rules", serde_json::to_string(&self.tasks.failure_rules)?));
         kvs.push(("metrics_auto_refresh_interval_secs", self.metrics.auto_refresh_interval_secs.to_string()));
+        kvs.push(("metrics_fixed_metrics", serde_json::to_string(&self.metrics.fixed_metrics)?));
+        kvs.push(("metrics_excluded_metrics", serde_json::to_string(&self.metrics.excluded_metrics)?));
+        kvs.push(("tensorboard_port_range_start", self.tensorboard.port_range_start.to_string()));
+        kvs.push(("tensorboard_port_range_end", self.tensorboard.port_range_end.to_string()));
+        kvs.push(("tensorboard_idle_timeout_secs", self.tensorboard.idle_timeout_secs.to_string()));
+        kvs.push(("registry_path", self.registry.path.to_string_lossy().into_owned()));
+        kvs.push(("evaluation_command_template", self.evaluation.command_template.clone()));
         let ignore_patterns_json = serde_json::to_string(&self.files.ignore_patterns)?;
         kvs.push(("files_ignore_patterns", ignore_patterns_json));
 
@@ -217,270 +356,32 @@ impl Config {
         // if !conda_script.exists() {
         //     anyhow::bail!("Conda script not found: {:?}", conda_script);
         // }
-        std::fs::create_dir_all(&self.storage.output_path)?;
-        std::fs::create_dir_all(&self.tasks.working_directory)?;
-        Ok(())
-    }
-}
-
-// src/metrics.rs
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-
-#[derive(Debug, Clone)]
-pub struct Metrics {
-    pub tasks_created: Arc<AtomicU64>,
-    pub tasks_completed: Arc<AtomicU64>,
-    pub tasks_failed: Arc<AtomicU64>,
-    pub sync_operations: Arc<AtomicU64>,
-    pub uptime_start: std::time::Instant,
-}
-
-impl Default for Metrics {
-    fn default() -> Self {
-        Self {
-            tasks_created: Arc::new(AtomicU64::new(0)),
-            tasks_completed: Arc::new(AtomicU64::new(0)),
-            tasks_failed: Arc::new(AtomicU64::new(0)),
-            sync_operations: Arc::new(AtomicU64::new(0)),
-            uptime_start: std::time::Instant::now(),
+        if self.tensorboard.port_range_start > self.tensorboard.port_range_end {
+            anyhow::bail!(
+                "Invalid TensorBoard port range: {}-{}",
+                self.tensorboard.port_range_start,
+                self.tensorboard.port_range_end
+            );
         }
-    }
-}
-
-#[derive(Serialize)]
-pub struct MetricsSnapshot {
-    pub tasks_created: u64,
-    pub tasks_completed: u64,
-    pub tasks_failed: u64,
-    pub sync_operations: u64,
-    pub uptime_seconds: u64,
-}
-
-impl Metrics {
-    pub fn snapshot(&self) -> MetricsSnapshot {
-        MetricsSnapshot {
-            tasks_created: self.tasks_created.load(Ordering::Relaxed),
-            tasks_completed: self.tasks_completed.load(Ordering::Relaxed),
-            tasks_failed: self.tasks_failed.load(Ordering::Relaxed),
-            sync_operations: self.sync_operations.load(Ordering::Relaxed),
-            uptime_seconds: self.uptime_start.elapsed().as_secs(),
+        if self.sync.max_upload_bytes == 0 {
+            anyhow::bail!("The maximum upload size must be at least 1 byte");
         }
-    }
-
-    pub fn increment_tasks_created(&self) {
-        self.tasks_created.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn increment_tasks_completed(&self) {
-        self.tasks_completed.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn increment_tasks_failed(&self) {
-        self.tasks_failed.fetch_add(1, Ordering::Relaxed);
-    }
-
-    pub fn increment_sync_operations(&self) {
-        self.sync_operations.fetch_add(1, Ordering::Relaxed);
-    }
-}
-
-// src/notifications.rs
-use tokio::sync::broadcast;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum NotificationType {
-    TaskCreated,
-    TaskStarted,
-    TaskCompleted,
-    TaskFailed,
-    TaskStopped,
-    SyncCompleted,
-    SyncFailed,
-    SystemError,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Notification {
-    pub id: String,
-    pub notification_type: NotificationType,
-    pub title: String,
-    pub message: String,
-    pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub task_id: Option<String>,
-}
-
-impl Notification {
-    pub fn new(
-        notification_type: NotificationType,
-        title: String,
-        message: String,
-        task_id: Option<String>,
-    ) -> Self {
-        Self {
-            id: uuid::Uuid::new_v4().to_string(),
-            notification_type,
-            title,
-            message,
-            timestamp: chrono::Utc::now(),
-            task_id,
+        if self.metrics.excluded_metrics.iter().any(|key| key.trim().is_empty()) {
+            anyhow::bail!("Excluded metrics must not contain empty entries");
         }
+        for rule in &self.tasks.failure_rules {
+            if rule.category.trim().is_empty() {
+                anyhow::bail!("Failure rules need a category");
+            }
+            rule.compile().map_err(anyhow::Error::msg)?;
+        }
+        let template = self.evaluation.command_template.trim();
+        if !template.is_empty() && !template.contains("{checkpoint}") {
+            anyhow::bail!("The evaluation command template must contain {{checkpoint}}");
+        }
+        std::fs::create_dir_all(&self.storage.output_path)?;
+        std::fs::create_dir_all(&self.tasks.working_directory)?;
+        std::fs::create_dir_all(&self.registry.path)?;
+        Ok(())
     }
-
-    pub fn task_created(task_name: &str, task_id: &str) -> Self {
-        Self::new(
-            NotificationType::TaskCreated,
-            "任务已创建".to_string(),
-            format!("任务 '{}' 已添加到队列", task_name),
-            Some(task_id.to_string()),
-        )
-    }
-
-    pub fn task_started(task_name: &str, task_id: &str) -> Self {
-        Self::new(
-            NotificationType::TaskStarted,
-            "任务已开始".to_string(),
-            format!("任务 '{}' 开始执行", task_name),
-            Some(task_id.to_string()),
-        )
-    }
-
-    pub fn task_completed(task_name: &str, task_id: &str) -> Self {
-        Self::new(
-            NotificationType::TaskCompleted,
-            "任务已完成".to_string(),
-            format!("任务 '{}' 执行完成", task_name),
-            Some(task_id.to_string()),
-        )
-    }
-
-    pub fn task_failed(task_name: &str, task_id: &str, error: &str) -> Self {
-        Self::new(
-            NotificationType::TaskFailed,
-            "任务执行失败".to_string(),
-            format!("任务 '{}' 执行失败: {}", task_name, error),
-            Some(task_id.to_string()),
-        )
-    }
-
-    pub fn sync_completed() -> Self {
-        Self::new(
-            NotificationType::SyncCompleted,
-            "代码同步完成".to_string(),
-            "代码同步操作已成功完成".to_string(),
-            None,
-        )
-    }
-
-    pub fn sync_failed(error: &str) -> Self {
-        Self::new(
-            NotificationType::SyncFailed,
-            "代码同步失败".to_string(),
-            format!("代码同步失败: {}", error),
-            None,
-        )
-    }
-}
-
-#[derive(Clone)]
-pub struct NotificationService {
-    sender: broadcast::Sender<Notification>,
-}
-
-impl NotificationService {
-    pub fn new() -> Self {
-        let (sender, _) = broadcast::channel(1000);
-        Self { sender }
-    }
-
-    pub fn send(&self, notification: Notification) {
-        let _ = self.sender.send(notification);
-    }
-
-    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
-        self.sender.subscribe()
-    }
-}
-
-// src/utils.rs
-use tokio::process::Command;
-
-pub async fn check_system_requirements() -> Result<()> {
-    // 检查Python
-    let python_output = Command::new("python")
-        .args(&["--version"])
-        .output()
-        .await?;
-    
-    if !python_output.status.success() {
-        anyhow::bail!("Python not found or not working");
-    }
-
-    // 检查rsync
-    let rsync_output = Command::new("rsync")
-        .args(&["--version"])
-        .output()
-        .await?;
-    
-    if !rsync_output.status.success() {
-        anyhow::bail!("rsync not found or not working");
-    }
-
-    // 检查TensorBoard
-    let tb_output = Command::new("tensorboard")
-        .args(&["--version"])
-        .output()
-        .await?;
-    
-    if !tb_output.status.success() {
-        tracing::warn!("TensorBoard not found, some features may not work");
-    }
-
-    Ok(())
-}
-
-pub fn format_duration(seconds: u64) -> String {
-    let hours = seconds / 3600;
-    let minutes = (seconds % 3600) / 60;
-    let seconds = seconds % 60;
-
-    if hours > 0 {
-        format!("{}h {}m {}s", hours, minutes, seconds)
-    } else if minutes > 0 {
-        format!("{}m {}s", minutes, seconds)
-    } else {
-        format!("{}s", seconds)
-    }
-}
-
-pub fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .map(|c| match c {
-            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
-            c => c,
-        })
-        .collect()
-}
-
-pub async fn get_system_info() -> serde_json::Value {
-    let mut sys = sysinfo::System::new_all();
-    sys.refresh_all();
-
-    let disks = sysinfo::Disks::new_with_refreshed_list();
-
-    serde_json::json!({
-        "hostname": sysinfo::System::host_name().unwrap_or_default(),
-        "os": format!("{} {}", sysinfo::System::name().unwrap_or_default(), sysinfo::System::os_version().unwrap_or_default()),
-        "cpu_count": sys.cpus().len(),
-        "cpu_usage": sys.global_cpu_usage(),
-        "memory_total": sys.total_memory(),
-        "memory_used": sys.used_memory(),
-        "disk_info": disks.iter().map(|disk| {
-            serde_json::json!({
-                "mount_point": disk.mount_point().to_str(),
-                "total_space": disk.total_space(),
-                "available_space": disk.available_space(),
-            })
-        }).collect::<Vec<_>>()
-    })
 }
@@ -13,14 +13,16 @@ pub enum AppError {
     Io(#[from] std::io::Error),
     #[error("Task not found: {0}")]
     TaskNotFound(String),
-    #[error("Task already running")]
-    TaskAlreadyRunning,
     #[error("Multipart error: {0}")]
     Multipart(#[from] MultipartError),
     #[error("Command failed: {0}")]
     CommandFailed(String),
     #[error("Config error: {0}")]
     Config(#[from] anyhow::Error),
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+    #[error("Not found: {0}")]
+    NotFound(String),
 }
 
 impl IntoResponse for AppError {
@@ -38,10 +40,6 @@ impl IntoResponse for AppError {
                 (StatusCode::INTERNAL_SERVER_ERROR, "IO error".to_string())
             }
             AppError::TaskNotFound(id) => (StatusCode::NOT_FOUND, format!("Task not found: {}", id)),
-            AppError::TaskAlreadyRunning => (
-                StatusCode::CONFLICT,
-                "Task already running".to_string(),
-            ),
             AppError::Multipart(err) => {
                 error!("Multipart error: {}", err);
                 (
@@ -63,6 +61,8 @@ impl IntoResponse for AppError {
                     "Configuration error".to_string(),
                 )
             }
+            AppError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            AppError::NotFound(message) => (StatusCode::NOT_FOUND, message),
         };
         (
             status,
@@ -0,0 +1,356 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use regex::Regex;
+use tracing::{error, info, warn};
+
+use crate::{
+    log_reader,
+    models::{AppState, CreateTaskRequest, Task, TaskStatus},
+    routes::{
+        checkpoints::{find_checkpoints, CheckpointInfo},
+        tasks::{new_task, submit_task, QueuePriority},
+    },
+    run_dir,
+};
+
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(30);
+/// Checkpoints written more recently than this may still be incomplete.
+const CHECKPOINT_SETTLE_SECS: i64 = 10;
+/// How long after a task has finished its last checkpoints are still picked up.
+const FINISHED_GRACE_SECS: i64 = 600;
+/// How much of the end of an evaluation log is searched for results.
+const RESULTS_SCAN_BYTES: u64 = 1024 * 1024;
+/// Namespace the results of evaluations are stored under on the evaluated task.
+pub const EVAL_METRIC_PREFIX: &str = "Eval/";
+/// The evaluation wrapper shipped with the manager, installed into the output
+/// directory for `{eval_script}`.
+const EVAL_SCRIPT: &str = include_str!("../scripts/eval_checkpoint.py");
+const EVAL_SCRIPT_NAME: &str = "eval_checkpoint.py";
+
+// Evaluation commands report their results on lines of the form
+//
+//     [EVAL] success_rate: 0.93
+//     [EVAL] episode_return: 41.7
+//
+// which end up as the `Eval/success_rate` and `Eval/episode_return` series of the
+// evaluated task, at the iteration of the evaluated checkpoint. When a key is printed
+// more than once, the last value counts. The shipped `scripts/eval_checkpoint.py`
+// wraps Isaac Lab's play scripts to print these lines.
+
+// --- Evaluation Scheduler Background Service ---
+
+/// Queues an evaluation task for every N-th checkpoint saved by tasks that ask for
+/// evaluations. Evaluations are queued with low priority: they only start while no
+/// other task is queued or running, so they run one at a time and never hold up
+/// training runs.
+pub struct EvaluationScheduler {
+    state: AppState,
+}
+
+impl EvaluationScheduler {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.schedule_evaluations().await {
+                error!("Failed to schedule checkpoint evaluations: {}", e);
+            }
+            tokio::time::sleep(SCHEDULE_INTERVAL).await;
+        }
+    }
+
+    async fn schedule_evaluations(&self) -> Result<()> {
+        let (template, output_path) = {
+            let config = self.state.config.read().await;
+            (config.evaluation.command_template.clone(), config.storage.output_path.clone())
+        };
+        if template.trim().is_empty() {
+            return Ok(());
+        }
+        let eval_script = install_eval_script(&output_path).await?;
+
+        let finished_since = Utc::now() - chrono::Duration::seconds(FINISHED_GRACE_SECS);
+        let tasks = sqlx::query_as::<_, Task>(
+            "SELECT * FROM tasks WHERE eval_every > 0 AND (status = ? OR finished_at >= ?)",
+        )
+        .bind(TaskStatus::Running)
+        .bind(finished_since)
+        .fetch_all(&self.state.db)
+        .await?;
+
+        for task in tasks {
+            if let Err(e) = self.schedule_task_evaluations(&task, &template, &eval_script).await {
+                warn!("Failed to schedule evaluations of task {}: {}", task.id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn schedule_task_evaluations(&self, task: &Task, template: &str, eval_script: &Path) -> Result<()> {
+        let Some(every) = task.eval_every.filter(|every| *every > 0) else {
+            return Ok(());
+        };
+        let Some(run_dir) = run_dir::task_run_dir(&self.state, task).await? else {
+            return Ok(());
+        };
+        let dir = run_dir.clone();
+        let checkpoints = tokio::task::spawn_blocking(move || find_checkpoints(&dir)).await?;
+
+        let evaluated: HashSet<i64> = sqlx::query_scalar(
+            "SELECT eval_iteration FROM tasks WHERE eval_source_task_id = ? AND eval_iteration IS NOT NULL",
+        )
+        .bind(&task.id)
+        .fetch_all(&self.state.db)
+        .await?
+        .into_iter()
+        .collect();
+
+        let settled_before = Utc::now() - chrono::Duration::seconds(CHECKPOINT_SETTLE_SECS);
+        let due = every_nth(&checkpoints, every)
+            .into_iter()
+            .filter(|(checkpoint, iteration)| {
+                !evaluated.contains(iteration) && checkpoint.modified_at < settled_before
+            });
+        for (checkpoint, iteration) in due {
+            let checkpoint_path = run_dir.join(&checkpoint.name);
+            let paths = TemplatePaths {
+                run_dir: &run_dir,
+                checkpoint: &checkpoint_path,
+                eval_script,
+            };
+            let isaac_task = isaac_task(&task.command).unwrap_or(&task.name);
+            let command = expand_template(template, isaac_task, &paths, iteration);
+            let mut evaluation = new_task(
+                &self.state,
+                CreateTaskRequest {
+                    command,
+                    conda_env: task.conda_env.clone(),
+                    working_dir: task.working_dir.clone(),
+                    log_format: None,
+                    metric_extractors: Vec::new(),
+                    eval_every: None,
+                },
+            )
+            .await;
+            evaluation.name = format!("{} [eval @ {}]", task.name, iteration);
+            evaluation.eval_source_task_id = Some(task.id.clone());
+            evaluation.eval_iteration = Some(iteration);
+            submit_task(&self.state, &evaluation, QueuePriority::Low).await?;
+            info!(
+                "Queued evaluation {} of checkpoint {} of task {}",
+                evaluation.id, checkpoint.name, task.id
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The N-th, 2N-th, ... checkpoint with an iteration, with that iteration. Counted by
+/// position rather than iteration, so the interval between saves does not matter.
+fn every_nth(checkpoints: &[CheckpointInfo], every: i64) -> Vec<(&CheckpointInfo, i64)> {
+    checkpoints
+        .iter()
+        .filter_map(|checkpoint| Some((checkpoint, checkpoint.iteration?)))
+        .enumerate()
+        .filter(|(position, _)| (*position as i64 + 1) % every == 0)
+        .map(|(_, due)| due)
+        .collect()
+}
+
+/// Writes the evaluation wrapper to the output directory, unless it is already there,
+/// and returns its absolute path.
+async fn install_eval_script(output_path: &Path) -> Result<PathBuf> {
+    let path = std::path::absolute(output_path.join(EVAL_SCRIPT_NAME))?;
+    if tokio::fs::read_to_string(&path).await.ok().as_deref() != Some(EVAL_SCRIPT) {
+        tokio::fs::write(&path, EVAL_SCRIPT).await?;
+    }
+    Ok(path)
+}
+
+struct TemplatePaths<'a> {
+    run_dir: &'a Path,
+    checkpoint: &'a Path,
+    eval_script: &'a Path,
+}
+
+fn expand_template(template: &str, isaac_task: &str, paths: &TemplatePaths, iteration: i64) -> String {
+    template
+        .replace("{checkpoint}", &paths.checkpoint.to_string_lossy())
+        .replace("{run_dir}", &paths.run_dir.to_string_lossy())
+        .replace("{eval_script}", &paths.eval_script.to_string_lossy())
+        .replace("{iteration}", &iteration.to_string())
+        .replace("{task}", isaac_task)
+}
+
+/// The Isaac Lab task a command trains, from its `--task` argument.
+fn isaac_task(command: &str) -> Option<&str> {
+    let mut parts = command.split_whitespace();
+    while let Some(part) = parts.next() {
+        if part == "--task" {
+            return parts.next();
+        }
+        if let Some(task) = part.strip_prefix("--task=") {
+            return Some(task);
+        }
+    }
+    None
+}
+
+/// Stores the results an evaluation task printed on the task it evaluated. Does
+/// nothing for tasks that are not evaluations.
+pub async fn record_results(state: &AppState, task: &Task, log_path: &Path) -> Result<()> {
+    let (Some(source_task_id), Some(iteration)) = (&task.eval_source_task_id, task.eval_iteration) else {
+        return Ok(());
+    };
+    let path = PathBuf::from(log_path);
+    let tail = tokio::task::spawn_blocking(move || -> std::io::Result<Vec<u8>> {
+        let len = std::fs::metadata(&path)?.len();
+        let start = len.saturating_sub(RESULTS_SCAN_BYTES);
+        log_reader::read_from(&path, start, RESULTS_SCAN_BYTES as usize)
+    })
+    .await??;
+    let results = parse_results(&String::from_utf8_lossy(&tail));
+    if results.is_empty() {
+        warn!("Evaluation {} printed no results", task.id);
+        return Ok(());
+    }
+
+    let wall_time = Utc::now();
+    let mut tx = state.db.begin().await?;
+    for (key, value) in &results {
+        sqlx::query(
+            "INSERT INTO metrics (task_id, iteration, key, value, wall_time) VALUES (?, ?, ?, ?, ?)
+             ON CONFLICT(task_id, key, iteration) DO UPDATE SET value = excluded.value, wall_time = excluded.wall_time",
+        )
+        .bind(source_task_id)
+        .bind(iteration)
+        .bind(format!("{}{}", EVAL_METRIC_PREFIX, key))
+        .bind(value)
+        .bind(wall_time)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+    info!(
+        "Recorded {} evaluation results of task {} at iteration {}",
+        results.len(),
+        source_task_id,
+        iteration
+    );
+    Ok(())
+}
+
+fn parse_results(log: &str) -> HashMap<String, f64> {
+    let result_regex =
+        Regex::new(r"\[EVAL\]\s*([^:\r\n]+?)\s*:\s*([-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?)").unwrap();
+    result_regex
+        .captures_iter(log)
+        .filter_map(|captures| Some((captures[1].to_string(), captures[2].parse().ok()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(name: &str, iteration: Option<i64>) -> CheckpointInfo {
+        CheckpointInfo {
+            name: name.to_string(),
+            iteration,
+            size: 0,
+            modified_at: Utc::now(),
+            metrics: HashMap::new(),
+        }
+    }
+
+    use crate::config::DEFAULT_EVALUATION_TEMPLATE;
+
+    #[test]
+    fn selects_every_nth_checkpoint_by_position() {
+        let checkpoints = vec![
+            checkpoint("model_0.pt", Some(0)),
+            checkpoint("model_50.pt", Some(50)),
+            checkpoint("model_best.pt", None),
+            checkpoint("model_100.pt", Some(100)),
+            checkpoint("model_300.pt", Some(300)),
+            checkpoint("model_301.pt", Some(301)),
+        ];
+        let iterations = |every| -> Vec<i64> {
+            every_nth(&checkpoints, every).into_iter().map(|(_, iteration)| iteration).collect()
+        };
+        assert_eq!(iterations(1), vec![0, 50, 100, 300, 301]);
+        assert_eq!(iterations(2), vec![50, 300]);
+        assert_eq!(iterations(5), vec![301]);
+        assert!(iterations(6).is_empty());
+    }
+
+    #[test]
+    fn expands_template() {
+        let paths = TemplatePaths {
+            run_dir: Path::new("/runs/ant"),
+            checkpoint: Path::new("/runs/ant/model_500.pt"),
+            eval_script: Path::new("/outputs/eval_checkpoint.py"),
+        };
+        assert_eq!(
+            expand_template(DEFAULT_EVALUATION_TEMPLATE, "Isaac-Ant-v0", &paths, 500),
+            "python /outputs/eval_checkpoint.py --task Isaac-Ant-v0 --checkpoint /runs/ant/model_500.pt --num_envs 64 --headless"
+        );
+        assert_eq!(
+            expand_template("eval {run_dir} {iteration} {task}", "Isaac-Ant-v0", &paths, 500),
+            "eval /runs/ant 500 Isaac-Ant-v0"
+        );
+    }
+
+    #[test]
+    fn finds_isaac_task_in_command() {
+        let command = "python scripts/reinforcement_learning/rsl_rl/train.py --task Isaac-Ant-v0 --headless";
+        assert_eq!(isaac_task(command), Some("Isaac-Ant-v0"));
+        assert_eq!(isaac_task("python train.py --task=Isaac-Humanoid-v0"), Some("Isaac-Humanoid-v0"));
+        assert_eq!(isaac_task("python my_train.py --tasks 3"), None);
+    }
+
+    #[tokio::test]
+    async fn installs_eval_script() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = install_eval_script(dir.path()).await.unwrap();
+        assert!(path.is_absolute());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), EVAL_SCRIPT);
+
+        std::fs::write(&path, "outdated").unwrap();
+        install_eval_script(dir.path()).await.unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), EVAL_SCRIPT);
+    }
+
+    #[test]
+    fn parses_results() {
+        let log = "\
+Loading checkpoint
+[EVAL] success_rate: 0.93
+[EVAL]episode_return : -41.7
+[EVAL] tracking error: 1.5e-3
+[EVAL] success_rate: 0.95
+[EVAL] note: n/a
+[INFO] reward: 12
+";
+        let results = parse_results(log);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results["success_rate"], 0.95);
+        assert_eq!(results["episode_return"], -41.7);
+        assert_eq!(results["tracking error"], 1.5e-3);
+    }
+
+    #[test]
+    fn parses_results_with_windows_line_endings() {
+        let results = parse_results("[EVAL] success_rate: .5\r\n[EVAL] steps: 100\r\n");
+        assert_eq!(results["success_rate"], 0.5);
+        assert_eq!(results["steps"], 100.0);
+    }
+}
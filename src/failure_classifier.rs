@@ -0,0 +1,231 @@
+use std::{io::SeekFrom, path::Path};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs as tokio_fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+// --- Failure Classification ---
+//
+// A failed task is diagnosed from the end of its log: the first rule with a match
+// there names the category, and the last matching line becomes the excerpt.
+
+/// How much of the end of the log is scanned for failure signatures.
+const TAIL_SCAN_BYTES: u64 = 64 * 1024;
+
+/// Longest excerpt stored on a task, in characters.
+const MAX_EXCERPT_CHARS: usize = 1000;
+
+/// Category of failures only recognized by their Python traceback.
+pub const PYTHON_EXCEPTION_CATEGORY: &str = "python_exception";
+
+/// Known failure signatures, checked in order after the user's rules.
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    // Isaac Sim waits for the EULA to be accepted on stdin the first time it runs.
+    (
+        "license_prompt",
+        r"(?i)accept the EULA|OMNI_KIT_ACCEPT_EULA|Omniverse License Agreement",
+    ),
+    (
+        "cuda_oom",
+        r"(?i)CUDA out of memory|CUDA error: out of memory|OutOfMemoryError",
+    ),
+    ("vulkan_device_lost", r"(?i)ERROR_DEVICE_LOST|vulkan.*device lost"),
+    // torch rejects the action distribution once the policy outputs NaN.
+    (
+        "nan_actions",
+        r"(?i)but found invalid values|\bnan\b.*\bactions?\b|\bactions?\b.*\bnan\b",
+    ),
+    ("module_not_found", r"ModuleNotFoundError: "),
+    (
+        "hydra_config",
+        r"hydra\.errors\.\w+|omegaconf\.errors\.\w+|Error executing job with overrides",
+    ),
+];
+
+/// A user-defined failure signature: logs with a line matching `pattern` are
+/// classified as `category`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FailureRule {
+    pub category: String,
+    pub pattern: String,
+}
+
+impl FailureRule {
+    pub fn compile(&self) -> Result<Regex, String> {
+        Regex::new(&self.pattern)
+            .map_err(|e| format!("Invalid pattern for failure category '{}': {}", self.category, e))
+    }
+}
+
+/// Why a task failed, as far as its log tells.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnosis {
+    pub category: String,
+    /// The log lines showing the error.
+    pub excerpt: String,
+}
+
+pub struct FailureClassifier {
+    rules: Vec<(String, Regex)>,
+}
+
+impl FailureClassifier {
+    /// The user's rules, which take precedence, followed by the built-in ones. User
+    /// rules with an invalid pattern are skipped; they are rejected by the config.
+    pub fn new(user_rules: &[FailureRule]) -> Self {
+        let user = user_rules
+            .iter()
+            .filter_map(|rule| Some((rule.category.clone(), rule.compile().ok()?)));
+        let builtin = BUILTIN_RULES
+            .iter()
+            .map(|(category, pattern)| (category.to_string(), Regex::new(pattern).unwrap()));
+        Self {
+            rules: user.chain(builtin).collect(),
+        }
+    }
+
+    /// Classifies the end of a log. Falls back to the root cause of the last Python
+    /// traceback when no rule matches.
+    pub fn classify(&self, log: &str) -> Option<Diagnosis> {
+        for (category, regex) in &self.rules {
+            if let Some(line) = log.lines().rev().find(|line| regex.is_match(line)) {
+                return Some(Diagnosis {
+                    category: category.clone(),
+                    excerpt: truncate(line.trim()),
+                });
+            }
+        }
+        traceback_root_cause(log).map(|excerpt| Diagnosis {
+            category: PYTHON_EXCEPTION_CATEGORY.to_string(),
+            excerpt,
+        })
+    }
+
+    /// Classifies the tail of the log file at `path`.
+    pub async fn classify_log(&self, path: &Path) -> Option<Diagnosis> {
+        let mut file = tokio_fs::File::open(path).await.ok()?;
+        let len = file.metadata().await.ok()?.len();
+        let start = len.saturating_sub(TAIL_SCAN_BYTES);
+        file.seek(SeekFrom::Start(start)).await.ok()?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).await.ok()?;
+        let tail = String::from_utf8_lossy(&buffer);
+        // The first line is cut off when reading starts in the middle of the file.
+        let tail = match tail.find('\n') {
+            Some(newline) if start > 0 => &tail[newline + 1..],
+            _ => &tail,
+        };
+        self.classify(tail)
+    }
+}
+
+/// The innermost frame and the exception of the last traceback in the log.
+fn traceback_root_cause(log: &str) -> Option<String> {
+    let start = log.rfind("Traceback (most recent call last):")?;
+    let mut frame: Vec<&str> = Vec::new();
+    for line in log[start..].lines().skip(1) {
+        if line.trim_start().starts_with("File \"") {
+            frame = vec![line.trim()];
+        } else if line.starts_with(char::is_whitespace) {
+            frame.push(line.trim());
+        } else if !line.trim().is_empty() {
+            frame.push(line.trim());
+            return Some(truncate(&frame.join("\n")));
+        }
+    }
+    None
+}
+
+fn truncate(excerpt: &str) -> String {
+    match excerpt.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}...", &excerpt[..end]),
+        None => excerpt.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn classify(log: &str) -> Diagnosis {
+        FailureClassifier::new(&[]).classify(log).expect("log should be classified")
+    }
+
+    #[test]
+    fn detects_cuda_oom() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/cuda_oom.log"));
+        assert_eq!(diagnosis.category, "cuda_oom");
+        assert!(diagnosis.excerpt.starts_with("torch.OutOfMemoryError: CUDA out of memory"));
+    }
+
+    #[test]
+    fn detects_missing_module() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/module_not_found.log"));
+        assert_eq!(diagnosis.category, "module_not_found");
+        assert_eq!(diagnosis.excerpt, "ModuleNotFoundError: No module named 'rsl_rl'");
+    }
+
+    #[test]
+    fn detects_license_prompt() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/license.log"));
+        assert_eq!(diagnosis.category, "license_prompt");
+        assert_eq!(diagnosis.excerpt, "Do you accept the EULA? (Yes/No):");
+    }
+
+    #[test]
+    fn detects_vulkan_device_lost() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/vulkan_device_lost.log"));
+        assert_eq!(diagnosis.category, "vulkan_device_lost");
+        assert!(diagnosis.excerpt.ends_with("VkResult: ERROR_DEVICE_LOST"));
+    }
+
+    #[test]
+    fn detects_nan_actions() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/nan_actions.log"));
+        assert_eq!(diagnosis.category, "nan_actions");
+        assert!(diagnosis.excerpt.starts_with("ValueError: Expected parameter loc"));
+    }
+
+    #[test]
+    fn detects_hydra_config_errors() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/hydra_config.log"));
+        assert_eq!(diagnosis.category, "hydra_config");
+        assert_eq!(
+            diagnosis.excerpt,
+            "hydra.errors.ConfigCompositionException: Could not override 'agent.max_iteration'."
+        );
+    }
+
+    #[test]
+    fn falls_back_to_traceback_root_cause() {
+        let diagnosis = classify(include_str!("../tests/fixtures/failures/python_traceback.log"));
+        assert_eq!(diagnosis.category, PYTHON_EXCEPTION_CATEGORY);
+        assert_eq!(
+            diagnosis.excerpt,
+            "File \"/workspace/my_tasks/my_tasks/rewards.py\", line 42, in feet_contact_reward\n\
+             return torch.sum(contacts[:, self.feet_ids], dim=1)\n\
+             KeyError: 'feet_ids'"
+        );
+    }
+
+    #[test]
+    fn user_rules_take_precedence() {
+        let classifier = FailureClassifier::new(&[FailureRule {
+            category: "reward_bug".to_string(),
+            pattern: r"in feet_contact_reward".to_string(),
+        }]);
+        let diagnosis = classifier
+            .classify(include_str!("../tests/fixtures/failures/python_traceback.log"))
+            .unwrap();
+        assert_eq!(diagnosis.category, "reward_bug");
+    }
+
+    #[test]
+    fn training_logs_are_not_classified() {
+        let log = include_str!("../tests/fixtures/metrics/rsl_rl.log");
+        assert_eq!(FailureClassifier::new(&[]).classify(log), None);
+    }
+}
@@ -0,0 +1,170 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use sqlx::SqlitePool;
+use tokio::fs as tokio_fs;
+use tracing::{error, warn};
+
+use crate::{
+    log_reader,
+    models::{AppState, TaskStatus},
+    terminal,
+};
+
+const INDEX_INTERVAL: Duration = Duration::from_secs(30);
+/// Bytes of log text indexed per transaction.
+const BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+/// A task log that still has unindexed content, with the progress made so far.
+#[derive(sqlx::FromRow)]
+struct PendingLog {
+    task_id: String,
+    log_path: String,
+    status: TaskStatus,
+    file_id: Option<i64>,
+    byte_offset: Option<i64>,
+    line_count: Option<i64>,
+}
+
+// --- Log Indexer Background Service ---
+
+/// Feeds task logs into the `log_index` FTS5 table. Each log is indexed incrementally
+/// from where the previous pass stopped; once a finished task's log has been read to
+/// the end it is marked complete and no longer visited.
+pub struct LogIndexer {
+    state: AppState,
+}
+
+impl LogIndexer {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.index_pending_logs().await {
+                error!("Failed to update the log index: {}", e);
+            }
+            tokio::time::sleep(INDEX_INTERVAL).await;
+        }
+    }
+
+    async fn index_pending_logs(&self) -> Result<()> {
+        let pending = sqlx::query_as::<_, PendingLog>(
+            "SELECT tasks.id AS task_id, tasks.log_path, tasks.status, s.file_id, s.byte_offset, s.line_count
+             FROM tasks LEFT JOIN log_index_state s ON s.task_id = tasks.id
+             WHERE tasks.log_path IS NOT NULL AND COALESCE(s.complete, 0) = 0",
+        )
+        .fetch_all(&self.state.db)
+        .await?;
+
+        for log in pending {
+            if let Err(e) = self.index_log(&log).await {
+                warn!("Failed to index log of task {}: {}", log.task_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn index_log(&self, log: &PendingLog) -> Result<()> {
+        let path = PathBuf::from(&log.log_path);
+        let Ok(metadata) = tokio_fs::metadata(&path).await else {
+            // The task has not written its log yet.
+            return Ok(());
+        };
+        let file_id = log_reader::file_identity(&metadata) as i64;
+        let finished = !matches!(log.status, TaskStatus::Queued | TaskStatus::Running);
+
+        let (mut offset, mut line_count) = match (log.file_id, log.byte_offset, log.line_count) {
+            (Some(known), Some(offset), Some(lines))
+                if known == file_id && offset as u64 <= metadata.len() =>
+            {
+                (offset as u64, lines)
+            }
+            (Some(_), _, _) => {
+                // The log was truncated or replaced (e.g. by a retry); start over.
+                remove_from_index(&self.state.db, &log.task_id).await?;
+                (0, 0)
+            }
+            _ => (0, 0),
+        };
+
+        loop {
+            let read_path = path.clone();
+            let bytes = tokio::task::spawn_blocking(move || {
+                log_reader::read_from(&read_path, offset, BATCH_BYTES)
+            })
+            .await??;
+            // Only whole lines are indexed while the task is still writing.
+            let indexed_len = match bytes.iter().rposition(|&b| b == b'\n') {
+                Some(i) => i + 1,
+                None if finished || bytes.len() == BATCH_BYTES => bytes.len(),
+                None => 0,
+            };
+            let done = finished && indexed_len == bytes.len() && bytes.len() < BATCH_BYTES;
+            if indexed_len == 0 && !done {
+                break;
+            }
+
+            let text = String::from_utf8_lossy(&bytes[..indexed_len]);
+            let text = terminal::normalize(&text);
+            let first_line = line_count;
+            line_count += text.lines().count() as i64;
+            offset += indexed_len as u64;
+            let mut tx = self.state.db.begin().await?;
+            // Recording the progress first takes the write lock, so a task deleted
+            // before this point gets no rows and one deleted later has them removed
+            // by its delete handler.
+            let recorded = sqlx::query(
+                "INSERT INTO log_index_state (task_id, file_id, byte_offset, line_count, complete, updated_at)
+                 SELECT ?, ?, ?, ?, ?, ? WHERE EXISTS (SELECT 1 FROM tasks WHERE id = ?)
+                 ON CONFLICT(task_id) DO UPDATE SET file_id = excluded.file_id, byte_offset = excluded.byte_offset,
+                 line_count = excluded.line_count, complete = excluded.complete, updated_at = excluded.updated_at",
+            )
+            .bind(&log.task_id)
+            .bind(file_id)
+            .bind(offset as i64)
+            .bind(line_count)
+            .bind(done)
+            .bind(chrono::Utc::now())
+            .bind(&log.task_id)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if recorded == 0 {
+                // The task was deleted during this pass.
+                return Ok(());
+            }
+            for (line_number, line) in (first_line + 1..).zip(text.lines()) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                sqlx::query("INSERT INTO log_index (content, task_id, line_number) VALUES (?, ?, ?)")
+                    .bind(line)
+                    .bind(&log.task_id)
+                    .bind(line_number)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+
+            if done || bytes.len() < BATCH_BYTES {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Drops everything indexed for a task.
+pub async fn remove_from_index(db: &SqlitePool, task_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM log_index WHERE task_id = ?")
+        .bind(task_id)
+        .execute(db)
+        .await?;
+    sqlx::query("DELETE FROM log_index_state WHERE task_id = ?")
+        .bind(task_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
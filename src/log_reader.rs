@@ -0,0 +1,458 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use regex::Regex;
+use serde::Serialize;
+
+/// Size of the blocks used when scanning a log.
+const READ_BLOCK: usize = 64 * 1024;
+/// Longest line kept when searching a log. Progress bars redrawn with `\r` can run for
+/// megabytes without a newline; only the start of such a line is searched.
+const MAX_GREP_LINE_BYTES: usize = 256 * 1024;
+
+/// A run of lines read from a log, together with the byte range they occupy.
+#[derive(Debug)]
+pub struct LogChunk {
+    pub lines: Vec<String>,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Identifies the file behind a path so a rotated log can be told apart from a grown one.
+#[cfg(unix)]
+pub fn file_identity(metadata: &std::fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+pub fn file_identity(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+/// Reads the last `n` complete lines of a log without loading the whole file.
+/// A trailing line that has not been terminated by a newline yet is left out,
+/// so `end` is always the offset right after the last newline.
+pub fn tail_lines(path: &Path, n: usize) -> std::io::Result<LogChunk> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let end = match find_newlines_backward(&mut file, len, 1)?.first() {
+        Some(&after_newline) => after_newline,
+        None => 0,
+    };
+    lines_before_in(&mut file, end, 0, n)
+}
+
+/// Reads `n` lines ending at byte `end` (or the end of the file), after skipping the
+/// `skip` lines closest to it. Data between the last newline and `end` counts as a line.
+pub fn lines_before(path: &Path, end: Option<u64>, skip: usize, n: usize) -> std::io::Result<LogChunk> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    lines_before_in(&mut file, end.unwrap_or(len).min(len), skip, n)
+}
+
+fn lines_before_in(file: &mut File, end: u64, skip: usize, n: usize) -> std::io::Result<LogChunk> {
+    // A newline right before `end` terminates the last line rather than starting a new one.
+    let search_end = if end > 0 && byte_at(file, end - 1)? == b'\n' {
+        end - 1
+    } else {
+        end
+    };
+    // Line boundaries (offsets right after a newline), nearest first.
+    let boundaries = find_newlines_backward(file, search_end, skip.saturating_add(n))?;
+    let boundary = |k: usize| -> u64 {
+        if k == 0 {
+            end
+        } else {
+            boundaries.get(k - 1).copied().unwrap_or(0)
+        }
+    };
+    let page_end = boundary(skip);
+    let page_start = if page_end == 0 { 0 } else { boundary(skip.saturating_add(n)) };
+    read_lines(file, page_start, page_end)
+}
+
+/// Reads `n` lines starting at byte `start`, after skipping the first `skip` lines.
+pub fn lines_after(path: &Path, start: u64, skip: usize, n: usize) -> std::io::Result<LogChunk> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let start = start.min(len);
+    let page_start = if skip == 0 {
+        start
+    } else {
+        let skipped = find_newlines_forward(&mut file, start, skip)?;
+        if skipped.len() == skip {
+            skipped[skip - 1]
+        } else {
+            len
+        }
+    };
+    let page_end = if n == 0 {
+        page_start
+    } else {
+        let newlines = find_newlines_forward(&mut file, page_start, n)?;
+        if newlines.len() == n {
+            newlines[n - 1]
+        } else {
+            len
+        }
+    };
+    read_lines(&mut file, page_start, page_end)
+}
+
+/// Reads at most `max_bytes` starting at `offset`.
+pub fn read_from(path: &Path, offset: u64, max_bytes: usize) -> std::io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    read_range(&mut file, offset, len.min(offset + max_bytes as u64))
+}
+
+/// Reads the byte range `[start, end)` of a log, clamped to the file size.
+pub fn read_bytes(path: &Path, start: u64, end: u64) -> std::io::Result<LogChunk> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    read_lines(&mut file, start.min(len), end.min(len))
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrepMatch {
+    pub line_number: u64,
+    pub line: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrepResult {
+    pub matches: Vec<GrepMatch>,
+    /// Set when `max_matches` was reached before the end of the log. Passing it back
+    /// as `cursor` continues the search where it stopped.
+    pub next_cursor: Option<String>,
+}
+
+/// Searches a log line by line starting at byte `start` (which is line `first_line`),
+/// keeping only `context` lines of history in memory. Lines longer than
+/// `MAX_GREP_LINE_BYTES` are cut off. Each line is passed through
+/// `render` before it is matched and returned. `max_matches` must be at least 1.
+pub fn grep(
+    path: &Path,
+    pattern: &Regex,
+    context: usize,
+    max_matches: usize,
+    start: u64,
+    first_line: u64,
+    render: impl Fn(String) -> String,
+) -> std::io::Result<GrepResult> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut reader = BufReader::with_capacity(READ_BLOCK, file);
+
+    let mut matches: Vec<GrepMatch> = Vec::new();
+    let mut before: VecDeque<String> = VecDeque::with_capacity(context + 1);
+    // Matches still collecting their trailing context.
+    let mut pending_after = 0;
+    let mut position = start;
+    let mut line_number = first_line;
+    let mut buffer = Vec::new();
+    let mut next_cursor = None;
+
+    loop {
+        buffer.clear();
+        let read = read_line_capped(&mut reader, &mut buffer, MAX_GREP_LINE_BYTES)?;
+        if read == 0 {
+            break;
+        }
+        position += read as u64;
+        let line = render(
+            String::from_utf8_lossy(&buffer)
+                .trim_end_matches(['\n', '\r'])
+                .to_string(),
+        );
+
+        let is_match = pattern.is_match(&line);
+        if matches.len() >= max_matches && (pending_after == 0 || is_match) {
+            // Resume at this line, which has not been searched yet.
+            next_cursor = Some(grep_cursor(position - read as u64, line_number));
+            break;
+        }
+
+        if pending_after > 0 {
+            let open = matches.len() - pending_after;
+            for m in &mut matches[open..] {
+                m.after.push(line.clone());
+            }
+            pending_after = matches[open..]
+                .iter()
+                .filter(|m| m.after.len() < context)
+                .count();
+        }
+
+        if is_match {
+            matches.push(GrepMatch {
+                line_number,
+                line: line.clone(),
+                before: before.iter().cloned().collect(),
+                after: Vec::new(),
+            });
+            if context > 0 {
+                pending_after += 1;
+            }
+        }
+
+        if context > 0 {
+            if before.len() == context {
+                before.pop_front();
+            }
+            before.push_back(line);
+        }
+        line_number += 1;
+    }
+
+    Ok(GrepResult {
+        matches,
+        next_cursor,
+    })
+}
+
+/// Reads a line like `read_until(b'\n')`, but keeps at most `max_len` bytes of it in
+/// `buffer` and skips the rest. Returns the number of bytes consumed.
+fn read_line_capped(reader: &mut impl BufRead, buffer: &mut Vec<u8>, max_len: usize) -> std::io::Result<usize> {
+    let mut consumed = 0;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok(consumed);
+        }
+        let (len, complete) = match available.iter().position(|&b| b == b'\n') {
+            Some(i) => (i + 1, true),
+            None => (available.len(), false),
+        };
+        let keep = len.min(max_len.saturating_sub(buffer.len()));
+        buffer.extend_from_slice(&available[..keep]);
+        reader.consume(len);
+        consumed += len;
+        if complete {
+            return Ok(consumed);
+        }
+    }
+}
+
+/// Grep cursors are `<byte offset>:<line number>` so that line numbers stay correct
+/// when a search is resumed in the middle of the file.
+fn grep_cursor(offset: u64, line_number: u64) -> String {
+    format!("{}:{}", offset, line_number)
+}
+
+/// Returns the byte offset and line number a grep cursor points at.
+pub fn parse_grep_cursor(cursor: &str) -> Option<(u64, u64)> {
+    cursor
+        .split_once(':')
+        .and_then(|(offset, line)| Some((offset.parse().ok()?, line.parse().ok()?)))
+}
+
+fn byte_at(file: &mut File, offset: u64) -> std::io::Result<u8> {
+    let mut byte = [0u8; 1];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Returns the offsets right after the last `count` newlines before `end`, nearest first.
+fn find_newlines_backward(file: &mut File, end: u64, count: usize) -> std::io::Result<Vec<u64>> {
+    let mut found = Vec::with_capacity(count.min(READ_BLOCK));
+    let mut pos = end;
+    let mut buffer = vec![0; READ_BLOCK];
+    while pos > 0 && found.len() < count {
+        let read_len = (READ_BLOCK as u64).min(pos) as usize;
+        pos -= read_len as u64;
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut buffer[..read_len])?;
+        for i in (0..read_len).rev() {
+            if buffer[i] == b'\n' {
+                found.push(pos + i as u64 + 1);
+                if found.len() == count {
+                    break;
+                }
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Returns the offsets right after the first `count` newlines at or after `start`.
+fn find_newlines_forward(file: &mut File, start: u64, count: usize) -> std::io::Result<Vec<u64>> {
+    let mut found = Vec::with_capacity(count.min(READ_BLOCK));
+    let mut pos = start;
+    let mut buffer = vec![0; READ_BLOCK];
+    file.seek(SeekFrom::Start(start))?;
+    while found.len() < count {
+        let read_len = file.read(&mut buffer)?;
+        if read_len == 0 {
+            break;
+        }
+        for (i, &byte) in buffer[..read_len].iter().enumerate() {
+            if byte == b'\n' {
+                found.push(pos + i as u64 + 1);
+                if found.len() == count {
+                    break;
+                }
+            }
+        }
+        pos += read_len as u64;
+    }
+    Ok(found)
+}
+
+fn read_lines(file: &mut File, start: u64, end: u64) -> std::io::Result<LogChunk> {
+    let bytes = read_range(file, start, end)?;
+    Ok(LogChunk {
+        lines: String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(String::from)
+            .collect(),
+        start,
+        end: end.max(start),
+    })
+}
+
+fn read_range(file: &mut File, start: u64, end: u64) -> std::io::Result<Vec<u8>> {
+    if end <= start {
+        return Ok(Vec::new());
+    }
+    let mut bytes = vec![0; (end - start) as usize];
+    file.seek(SeekFrom::Start(start))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    fn log_file(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    fn grep_all(path: &Path, pattern: &str, context: usize, max_matches: usize, cursor: Option<&str>) -> GrepResult {
+        let (start, first_line) = cursor.map_or((0, 1), |cursor| parse_grep_cursor(cursor).unwrap());
+        grep(path, &Regex::new(pattern).unwrap(), context, max_matches, start, first_line, |line| line).unwrap()
+    }
+
+    #[test]
+    fn tail_skips_unterminated_line() {
+        let file = log_file("one\ntwo\nthree\npartial");
+        let chunk = tail_lines(file.path(), 2).unwrap();
+        assert_eq!(chunk.lines, vec!["two", "three"]);
+        assert_eq!(chunk.end, "one\ntwo\nthree\n".len() as u64);
+    }
+
+    #[test]
+    fn reads_lines_before_end() {
+        let file = log_file("one\ntwo\nthree\npartial");
+        assert_eq!(lines_before(file.path(), None, 0, 2).unwrap().lines, vec!["three", "partial"]);
+        assert_eq!(lines_before(file.path(), None, 1, 2).unwrap().lines, vec!["two", "three"]);
+
+        let chunk = lines_before(file.path(), None, 2, 10).unwrap();
+        assert_eq!(chunk.lines, vec!["one", "two"]);
+        assert_eq!(chunk.start, 0);
+
+        // A newline right before `end` terminates the last line.
+        let end = "one\ntwo\n".len() as u64;
+        assert_eq!(lines_before(file.path(), Some(end), 0, 1).unwrap().lines, vec!["two"]);
+        assert!(lines_before(file.path(), Some(0), 0, 5).unwrap().lines.is_empty());
+    }
+
+    #[test]
+    fn reads_lines_after_start() {
+        let file = log_file("one\ntwo\nthree\npartial");
+        assert_eq!(lines_after(file.path(), 0, 0, 2).unwrap().lines, vec!["one", "two"]);
+        assert_eq!(lines_after(file.path(), 0, 1, 2).unwrap().lines, vec!["two", "three"]);
+        assert_eq!(lines_after(file.path(), 4, 2, 5).unwrap().lines, vec!["partial"]);
+
+        let chunk = lines_after(file.path(), 1000, 0, 5).unwrap();
+        assert!(chunk.lines.is_empty());
+        assert_eq!(chunk.start, "one\ntwo\nthree\npartial".len() as u64);
+    }
+
+    #[test]
+    fn greps_with_context() {
+        let file = log_file("error 1\nok\nerror 2\nok\nok\nerror 3");
+        let result = grep_all(file.path(), "error", 1, 10, None);
+        assert!(result.next_cursor.is_none());
+        let found: Vec<_> = result
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.before.clone(), m.after.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (1, vec![], vec!["ok".to_string()]),
+                (3, vec!["ok".to_string()], vec!["ok".to_string()]),
+                (6, vec!["ok".to_string()], vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn grep_cursor_resumes_search() {
+        let file = log_file("error 1\nok\nerror 2\nok\nerror 3\nok\n");
+        let first = grep_all(file.path(), "error", 1, 2, None);
+        assert_eq!(first.matches.iter().map(|m| m.line_number).collect::<Vec<_>>(), vec![1, 3]);
+        // The trailing context of the last match is collected before stopping.
+        assert_eq!(first.matches[1].after, vec!["ok"]);
+        let cursor = first.next_cursor.expect("search should stop at max_matches");
+        assert_eq!(parse_grep_cursor(&cursor), Some(("error 1\nok\nerror 2\nok\n".len() as u64, 5)));
+
+        let rest = grep_all(file.path(), "error", 1, 2, Some(&cursor));
+        assert_eq!(rest.matches.len(), 1);
+        assert_eq!(rest.matches[0].line_number, 5);
+        assert_eq!(rest.matches[0].line, "error 3");
+        assert!(rest.next_cursor.is_none());
+    }
+
+    #[test]
+    fn huge_skips_return_empty_pages() {
+        let file = log_file("one\ntwo\n");
+        let chunk = lines_before(file.path(), None, usize::MAX, 10).unwrap();
+        assert!(chunk.lines.is_empty());
+        assert!(lines_after(file.path(), 0, usize::MAX, 10).unwrap().lines.is_empty());
+    }
+
+    #[test]
+    fn grep_cuts_off_long_lines() {
+        let progress = "\r 50%".repeat(MAX_GREP_LINE_BYTES);
+        let file = log_file(&format!("start\n{}error\nerror 2\n", progress));
+        let result = grep_all(file.path(), "start|error", 0, 10, None);
+        let found: Vec<_> = result.matches.iter().map(|m| (m.line_number, m.line.as_str())).collect();
+        // The error at the end of the overlong line is cut off; line numbers and
+        // positions after it stay right.
+        assert_eq!(found, vec![(1, "start"), (3, "error 2")]);
+
+        let mut reader = std::io::BufReader::with_capacity(4, "abcdefgh\nij".as_bytes());
+        let mut buffer = Vec::new();
+        assert_eq!(read_line_capped(&mut reader, &mut buffer, 3).unwrap(), 9);
+        assert_eq!(buffer, b"abc");
+        buffer.clear();
+        assert_eq!(read_line_capped(&mut reader, &mut buffer, 3).unwrap(), 2);
+        assert_eq!(buffer, b"ij");
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert_eq!(parse_grep_cursor("12:3"), Some((12, 3)));
+        assert_eq!(parse_grep_cursor("12"), None);
+        assert_eq!(parse_grep_cursor("a:3"), None);
+    }
+}
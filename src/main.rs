@@ -7,13 +7,33 @@ use tracing::{error, info};
 
 mod config;
 mod error;
+mod evaluation;
+mod failure_classifier;
+mod log_indexer;
+mod log_reader;
+mod metric_groups;
+mod metrics;
+mod metrics_ingester;
 mod metrics_parser;
 mod models;
+mod notifications;
 mod routes;
+mod run_dir;
+mod series;
+mod stall_monitor;
 mod task_manager;
+mod tensorboard;
+mod terminal;
+mod tfevents;
 
+use evaluation::EvaluationScheduler;
+use log_indexer::LogIndexer;
+use metrics_ingester::MetricsIngester;
 use models::AppState;
+use notifications::NotificationService;
+use stall_monitor::StallMonitor;
 use task_manager::TaskManager;
+use tensorboard::{TensorboardManager, TensorboardReaper};
 
 /// IsaacLab Manager Server
 #[derive(Parser, Debug)]
@@ -54,12 +74,19 @@ async fn main() -> Result<()> {
         db: db.clone(),
         tasks: Arc::new(RwLock::new(HashMap::new())),
         queue: Arc::new(Mutex::new(Vec::new())),
-        current_task: Arc::new(Mutex::new(None)),
         config: Arc::new(RwLock::new(config)),
+        notifications: NotificationService::new(),
+        tensorboard: TensorboardManager::default(),
+        metrics: metrics::Metrics::default(),
     };
 
     let task_manager = TaskManager::new(state.clone());
     tokio::spawn(task_manager.run());
+    tokio::spawn(StallMonitor::new(state.clone()).run());
+    tokio::spawn(LogIndexer::new(state.clone()).run());
+    tokio::spawn(MetricsIngester::new(state.clone()).run());
+    tokio::spawn(TensorboardReaper::new(state.clone()).run());
+    tokio::spawn(EvaluationScheduler::new(state.clone()).run());
 
     let app = routes::create_router(state.clone());
 
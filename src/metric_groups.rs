@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+// --- Metric namespace tree ---
+//
+// IsaacLab names its metrics with `/`-separated namespaces such as
+// `Episode_Reward/feet_air_time` or `Metrics/base_velocity/error_vel_xy`.
+
+/// A namespace of metrics, with the metrics directly in it and its sub-namespaces.
+#[derive(Debug, Default, Serialize)]
+pub struct MetricGroup {
+    /// Last segment of the namespace; empty for the root.
+    pub name: String,
+    /// Full namespace, e.g. `Metrics/base_velocity`; empty for the root.
+    pub path: String,
+    /// Full keys of the metrics directly in this namespace.
+    pub metrics: Vec<String>,
+    pub groups: Vec<MetricGroup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<GroupSummary>,
+}
+
+/// What the metrics of a group add up to.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GroupSummary {
+    /// Sum of the group's metrics per iteration, e.g. the total of all reward terms.
+    Sum { points: Vec<(i64, f64)> },
+    /// Share of each metric in the group's total per iteration, e.g. how often each
+    /// termination cause ended the episodes.
+    Fractions {
+        series: HashMap<String, Vec<(i64, f64)>>,
+    },
+}
+
+impl MetricGroup {
+    /// Calls `f` with every series of the summaries in the tree.
+    pub fn for_each_summary_series(&mut self, f: &mut impl FnMut(&mut Vec<(i64, f64)>)) {
+        match &mut self.summary {
+            Some(GroupSummary::Sum { points }) => f(points),
+            Some(GroupSummary::Fractions { series }) => series.values_mut().for_each(&mut *f),
+            None => {}
+        }
+        for group in &mut self.groups {
+            group.for_each_summary_series(f);
+        }
+    }
+}
+
+/// Groups metric series by namespace. Reward groups are summarized with their sum
+/// and termination groups with the share of each cause.
+pub fn group_metrics(historical_metrics: &HashMap<String, Vec<(i64, f64)>>) -> MetricGroup {
+    let mut keys: Vec<&String> = historical_metrics.keys().collect();
+    keys.sort();
+    let mut root = Node::default();
+    for key in keys {
+        let mut segments: Vec<&str> = key.split('/').collect();
+        segments.pop();
+        let mut node = &mut root;
+        for segment in segments {
+            node = node.children.entry(segment.to_string()).or_default();
+        }
+        node.metrics.push(key.clone());
+    }
+    root.into_group(String::new(), String::new(), historical_metrics)
+}
+
+#[derive(Default)]
+struct Node {
+    metrics: Vec<String>,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn into_group(
+        self,
+        name: String,
+        path: String,
+        historical_metrics: &HashMap<String, Vec<(i64, f64)>>,
+    ) -> MetricGroup {
+        let series: Vec<(&str, &[(i64, f64)])> = self
+            .metrics
+            .iter()
+            .map(|key| (key.as_str(), historical_metrics[key].as_slice()))
+            .collect();
+        let lower_name = name.to_lowercase();
+        let summary = if series.is_empty() {
+            None
+        } else if lower_name.contains("reward") {
+            Some(GroupSummary::Sum { points: sum(&series) })
+        } else if lower_name.contains("termination") {
+            Some(GroupSummary::Fractions { series: fractions(&series) })
+        } else {
+            None
+        };
+
+        let groups = self
+            .children
+            .into_iter()
+            .map(|(child, node)| {
+                let child_path = if path.is_empty() {
+                    child.clone()
+                } else {
+                    format!("{}/{}", path, child)
+                };
+                node.into_group(child, child_path, historical_metrics)
+            })
+            .collect();
+        MetricGroup {
+            name,
+            path,
+            metrics: self.metrics,
+            groups,
+            summary,
+        }
+    }
+}
+
+/// Per-iteration sum of the series, over the series that have a value there.
+fn sum(series: &[(&str, &[(i64, f64)])]) -> Vec<(i64, f64)> {
+    let mut totals: BTreeMap<i64, f64> = BTreeMap::new();
+    for (_, points) in series {
+        for &(iteration, value) in *points {
+            *totals.entry(iteration).or_default() += value;
+        }
+    }
+    totals.into_iter().collect()
+}
+
+/// Each series divided by the per-iteration sum. Iterations whose sum is zero (no
+/// episode ended) are left out.
+fn fractions(series: &[(&str, &[(i64, f64)])]) -> HashMap<String, Vec<(i64, f64)>> {
+    let totals: HashMap<i64, f64> = sum(series).into_iter().collect();
+    series
+        .iter()
+        .map(|(key, points)| {
+            let shares = points
+                .iter()
+                .filter_map(|&(iteration, value)| {
+                    let total = totals[&iteration];
+                    (total != 0.0).then(|| (iteration, value / total))
+                })
+                .collect();
+            (key.to_string(), shares)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(entries: &[(&str, &[(i64, f64)])]) -> HashMap<String, Vec<(i64, f64)>> {
+        entries
+            .iter()
+            .map(|(key, points)| (key.to_string(), points.to_vec()))
+            .collect()
+    }
+
+    fn group<'a>(root: &'a MetricGroup, path: &str) -> &'a MetricGroup {
+        path.split('/').fold(root, |group, name| {
+            group.groups.iter().find(|child| child.name == name).unwrap()
+        })
+    }
+
+    #[test]
+    fn builds_the_namespace_tree() {
+        let root = group_metrics(&metrics(&[
+            ("Loss/value_function", &[(0, 1.0)]),
+            ("Metrics/base_velocity/error_vel_xy", &[(0, 0.5)]),
+            ("Metrics/base_velocity/error_vel_yaw", &[(0, 0.2)]),
+            ("Mean reward", &[(0, 3.0)]),
+        ]));
+        assert_eq!((root.name.as_str(), root.path.as_str()), ("", ""));
+        assert_eq!(root.metrics, vec!["Mean reward"]);
+        let names: Vec<&str> = root.groups.iter().map(|group| group.name.as_str()).collect();
+        assert_eq!(names, vec!["Loss", "Metrics"]);
+
+        let velocity = group(&root, "Metrics/base_velocity");
+        assert_eq!(velocity.path, "Metrics/base_velocity");
+        assert_eq!(
+            velocity.metrics,
+            vec!["Metrics/base_velocity/error_vel_xy", "Metrics/base_velocity/error_vel_yaw"]
+        );
+        assert!(group(&root, "Metrics").metrics.is_empty());
+        assert!(root.summary.is_none() && velocity.summary.is_none());
+    }
+
+    #[test]
+    fn sums_reward_terms_over_the_iterations_they_have() {
+        let root = group_metrics(&metrics(&[
+            ("Episode_Reward/track_lin_vel", &[(0, 1.0), (1, 2.0), (2, 3.0)]),
+            // Logged from iteration 1 on only.
+            ("Episode_Reward/feet_air_time", &[(1, 0.5), (2, -0.25)]),
+        ]));
+        let Some(GroupSummary::Sum { points }) = &group(&root, "Episode_Reward").summary else {
+            panic!("reward group without a sum");
+        };
+        assert_eq!(*points, vec![(0, 1.0), (1, 2.5), (2, 2.75)]);
+    }
+
+    #[test]
+    fn fractions_skip_iterations_without_terminations() {
+        let root = group_metrics(&metrics(&[
+            ("Episode_Termination/time_out", &[(0, 0.0), (1, 3.0), (2, 1.0)]),
+            ("Episode_Termination/base_contact", &[(0, 0.0), (1, 1.0), (2, 0.0)]),
+        ]));
+        let Some(GroupSummary::Fractions { series }) = &group(&root, "Episode_Termination").summary
+        else {
+            panic!("termination group without fractions");
+        };
+        assert_eq!(series["Episode_Termination/time_out"], vec![(1, 0.75), (2, 1.0)]);
+        assert_eq!(series["Episode_Termination/base_contact"], vec![(1, 0.25), (2, 0.0)]);
+    }
+}
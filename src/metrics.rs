@@ -0,0 +1,150 @@
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+/// Counters of what the manager has done since it started, exported on `/metrics`.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub tasks_created: Arc<AtomicU64>,
+    pub tasks_completed: Arc<AtomicU64>,
+    pub tasks_failed: Arc<AtomicU64>,
+    pub sync_operations: Arc<AtomicU64>,
+    pub uptime_start: std::time::Instant,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            tasks_created: Arc::new(AtomicU64::new(0)),
+            tasks_completed: Arc::new(AtomicU64::new(0)),
+            tasks_failed: Arc::new(AtomicU64::new(0)),
+            sync_operations: Arc::new(AtomicU64::new(0)),
+            uptime_start: std::time::Instant::now(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct MetricsSnapshot {
+    pub tasks_created: u64,
+    pub tasks_completed: u64,
+    pub tasks_failed: u64,
+    pub sync_operations: u64,
+    pub uptime_seconds: u64,
+}
+
+impl Metrics {
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            tasks_created: self.tasks_created.load(Ordering::Relaxed),
+            tasks_completed: self.tasks_completed.load(Ordering::Relaxed),
+            tasks_failed: self.tasks_failed.load(Ordering::Relaxed),
+            sync_operations: self.sync_operations.load(Ordering::Relaxed),
+            uptime_seconds: self.uptime_start.elapsed().as_secs(),
+        }
+    }
+
+    pub fn increment_tasks_created(&self) {
+        self.tasks_created.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_tasks_completed(&self) {
+        self.tasks_completed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_tasks_failed(&self) {
+        self.tasks_failed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn increment_sync_operations(&self) {
+        self.sync_operations.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Builds a response in the Prometheus text exposition format.
+#[derive(Default)]
+pub struct PrometheusText {
+    out: String,
+}
+
+impl PrometheusText {
+    /// Starts a metric family. Its samples must follow before the next family.
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+        self
+    }
+
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) -> &mut Self {
+        self.out.push_str(name);
+        if !labels.is_empty() {
+            self.out.push('{');
+            for (i, (label, label_value)) in labels.iter().enumerate() {
+                if i > 0 {
+                    self.out.push(',');
+                }
+                let _ = write!(self.out, "{}=\"{}\"", label, escape_label_value(label_value));
+            }
+            self.out.push('}');
+        }
+        let _ = writeln!(self.out, " {}", format_value(value));
+        self
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_families_and_samples() {
+        let mut text = PrometheusText::default();
+        text.family("tasks", "gauge", "Tasks by status.")
+            .sample("tasks", &[], 3.0)
+            .sample("tasks", &[("status", "running"), ("gpu", "0")], 1.5);
+        assert_eq!(
+            text.finish(),
+            "# HELP tasks Tasks by status.\n\
+             # TYPE tasks gauge\n\
+             tasks 3\n\
+             tasks{status=\"running\",gpu=\"0\"} 1.5\n"
+        );
+    }
+
+    #[test]
+    fn escapes_label_values() {
+        let mut text = PrometheusText::default();
+        text.sample("task", &[("task_name", "say \"hi\"\\n\nnext")], 1.0);
+        assert_eq!(text.finish(), "task{task_name=\"say \\\"hi\\\"\\\\n\\nnext\"} 1\n");
+    }
+
+    #[test]
+    fn formats_special_values() {
+        let values = [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 0.25, 1e21];
+        let formatted: Vec<String> = values.into_iter().map(format_value).collect();
+        assert_eq!(formatted, vec!["NaN", "+Inf", "-Inf", "0.25", "1000000000000000000000"]);
+    }
+}
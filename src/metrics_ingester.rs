@@ -0,0 +1,393 @@
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, SqlitePool};
+use tokio::fs as tokio_fs;
+use tracing::{error, info, warn};
+
+use crate::{
+    evaluation::EVAL_METRIC_PREFIX,
+    log_reader,
+    metrics_parser::{self, LogFormat, MetricExtractor, MetricRules, MetricsParser, ParserState},
+    models::{AppState, TaskStatus},
+    run_dir,
+};
+
+const INGEST_INTERVAL: Duration = Duration::from_secs(10);
+/// Bytes of log text parsed per transaction.
+const BATCH_BYTES: usize = 4 * 1024 * 1024;
+
+/// A task log whose metrics have not been fully ingested, with the progress made so far.
+#[derive(sqlx::FromRow)]
+struct PendingLog {
+    task_id: String,
+    log_path: String,
+    command: String,
+    log_format: Option<LogFormat>,
+    metric_extractors: Option<Json<Vec<MetricExtractor>>>,
+    status: TaskStatus,
+    working_dir: Option<String>,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+    run_dir: Option<String>,
+    total_iterations: Option<i64>,
+    file_id: Option<i64>,
+    byte_offset: Option<i64>,
+    current_iteration: Option<i64>,
+}
+
+// --- Metrics Ingester Background Service ---
+
+/// Copies the metrics printed in task logs into the `metrics` table. While a task runs
+/// only complete records are ingested; the last one follows once it has finished,
+/// after which the log is marked complete and no longer visited.
+pub struct MetricsIngester {
+    state: AppState,
+}
+
+impl MetricsIngester {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub async fn run(self) {
+        loop {
+            if let Err(e) = self.ingest_pending_logs().await {
+                error!("Failed to ingest training metrics: {}", e);
+            }
+            tokio::time::sleep(INGEST_INTERVAL).await;
+        }
+    }
+
+    async fn ingest_pending_logs(&self) -> Result<()> {
+        let pending = sqlx::query_as::<_, PendingLog>(
+            "SELECT tasks.id AS task_id, tasks.log_path, tasks.command, tasks.log_format, tasks.metric_extractors, tasks.status,
+                    tasks.working_dir, tasks.started_at, tasks.finished_at, tasks.run_dir, tasks.total_iterations,
+                    s.file_id, s.byte_offset, s.current_iteration
+             FROM tasks LEFT JOIN metrics_ingest_state s ON s.task_id = tasks.id
+             WHERE tasks.log_path IS NOT NULL AND COALESCE(s.complete, 0) = 0",
+        )
+        .fetch_all(&self.state.db)
+        .await?;
+
+        for log in pending {
+            if let Err(e) = self.ingest_log(&log).await {
+                warn!("Failed to ingest metrics of task {}: {}", log.task_id, e);
+            }
+            if log.run_dir.is_none() {
+                if let Err(e) = self.detect_run_dir(&log).await {
+                    warn!("Failed to detect run directory of task {}: {}", log.task_id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Links the task to the directory its training run writes to, once that exists.
+    async fn detect_run_dir(&self, log: &PendingLog) -> Result<()> {
+        let working_dir = match &log.working_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => self.state.config.read().await.tasks.working_directory.clone(),
+        };
+        let log_path = PathBuf::from(&log.log_path);
+        let (started_at, finished_at) = (log.started_at, log.finished_at);
+        let detected = tokio::task::spawn_blocking(move || {
+            run_dir::detect(&log_path, &working_dir, started_at, finished_at)
+        })
+        .await?;
+        if let Some(detected) = detected {
+            info!("Task {} writes its run to {}", log.task_id, detected.display());
+            sqlx::query("UPDATE tasks SET run_dir = ? WHERE id = ?")
+                .bind(detected.to_string_lossy())
+                .bind(&log.task_id)
+                .execute(&self.state.db)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn ingest_log(&self, log: &PendingLog) -> Result<()> {
+        let path = PathBuf::from(&log.log_path);
+        let Ok(metadata) = tokio_fs::metadata(&path).await else {
+            // The task has not written its log yet, or the log has been deleted.
+            return Ok(());
+        };
+        let file_id = log_reader::file_identity(&metadata) as i64;
+        let finished = !matches!(log.status, TaskStatus::Queued | TaskStatus::Running);
+        let log_parser = LogFormat::resolve(log.log_format, &log.command).parser();
+        let extractors = log.metric_extractors.as_ref().map_or(&[][..], |json| &json.0);
+        let rules = MetricRules::new(&self.state.config.read().await.metrics, extractors);
+
+        // A log that was truncated or replaced (e.g. by a retry) is read again from the
+        // start, and what was stored from it before is dropped.
+        let (mut offset, mut parser) = match (log.file_id, log.byte_offset, log.current_iteration) {
+            (Some(known), Some(offset), Some(current_iteration))
+                if known == file_id && offset as u64 <= metadata.len() =>
+            {
+                (offset as u64, ParserState { current_iteration })
+            }
+            _ => (0, ParserState::default()),
+        };
+        let mut restarted = offset == 0 && log.byte_offset.is_some();
+
+        loop {
+            let read_path = path.clone();
+            let bytes = tokio::task::spawn_blocking(move || {
+                log_reader::read_from(&read_path, offset, BATCH_BYTES)
+            })
+            .await??;
+            let at_end = bytes.len() < BATCH_BYTES;
+            let done = finished && at_end;
+            let ingested_len = ingestible_len(log_parser, &bytes, done);
+            if ingested_len == 0 && !done {
+                break;
+            }
+
+            let text = String::from_utf8_lossy(&bytes[..ingested_len]);
+            let metrics_parser::ParsedPoints {
+                points,
+                latest_fixed_metrics,
+            } = metrics_parser::parse_points(log_parser, &rules, &text, &mut parser);
+            let wall_times = wall_times(&points, log_parser.time_elapsed_key(), log.started_at);
+
+            let mut tx = self.state.db.begin().await?;
+            if restarted {
+                info!("Log of task {} was replaced, ingesting its metrics again", log.task_id);
+                // Evaluation results belong to checkpoints, not to the log.
+                sqlx::query("DELETE FROM metrics WHERE task_id = ? AND key NOT LIKE ?")
+                    .bind(&log.task_id)
+                    .bind(format!("{}%", EVAL_METRIC_PREFIX))
+                    .execute(&mut *tx)
+                    .await?;
+                sqlx::query("UPDATE tasks SET latest_fixed_metrics = NULL WHERE id = ?")
+                    .bind(&log.task_id)
+                    .execute(&mut *tx)
+                    .await?;
+                restarted = false;
+            }
+            for point in &points {
+                sqlx::query(
+                    "INSERT INTO metrics (task_id, iteration, key, value, wall_time) VALUES (?, ?, ?, ?, ?)
+                     ON CONFLICT(task_id, key, iteration) DO UPDATE SET value = excluded.value, wall_time = excluded.wall_time",
+                )
+                .bind(&log.task_id)
+                .bind(point.iteration)
+                .bind(&point.key)
+                .bind(point.value)
+                .bind(wall_times[&point.iteration])
+                .execute(&mut *tx)
+                .await?;
+            }
+            if !latest_fixed_metrics.is_empty() {
+                sqlx::query(
+                    "UPDATE tasks SET latest_fixed_metrics = json_patch(COALESCE(latest_fixed_metrics, '{}'), ?) WHERE id = ?",
+                )
+                .bind(Json(&latest_fixed_metrics))
+                .bind(&log.task_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+            let progress = progress(&points, log_parser, log.total_iterations);
+            sqlx::query(
+                "UPDATE tasks SET current_iteration = COALESCE(?, current_iteration),
+                 total_iterations = COALESCE(?, total_iterations), progress_pct = COALESCE(?, progress_pct),
+                 steps_per_sec = COALESCE(?, steps_per_sec), eta_seconds = COALESCE(?, eta_seconds)
+                 WHERE id = ?",
+            )
+            .bind(progress.current_iteration)
+            .bind(progress.total_iterations)
+            .bind(progress.progress_pct)
+            .bind(progress.steps_per_sec)
+            .bind(progress.eta_seconds)
+            .bind(&log.task_id)
+            .execute(&mut *tx)
+            .await?;
+            if done {
+                // Nothing is left to wait for; a successful run has done all its work.
+                sqlx::query(
+                    "UPDATE tasks SET eta_seconds = NULL,
+                     progress_pct = CASE WHEN status = ? AND progress_pct IS NOT NULL THEN 100.0 ELSE progress_pct END
+                     WHERE id = ?",
+                )
+                .bind(TaskStatus::Completed)
+                .bind(&log.task_id)
+                .execute(&mut *tx)
+                .await?;
+            }
+
+            offset += ingested_len as u64;
+            sqlx::query(
+                "INSERT INTO metrics_ingest_state (task_id, file_id, byte_offset, current_iteration, complete, updated_at)
+                 VALUES (?, ?, ?, ?, ?, ?)
+                 ON CONFLICT(task_id) DO UPDATE SET file_id = excluded.file_id, byte_offset = excluded.byte_offset,
+                 current_iteration = excluded.current_iteration, complete = excluded.complete, updated_at = excluded.updated_at",
+            )
+            .bind(&log.task_id)
+            .bind(file_id)
+            .bind(offset as i64)
+            .bind(parser.current_iteration)
+            .bind(done)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            if at_end {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How much of a batch can be ingested: its complete records, or all of it once the
+/// task is done. A batch without the end of a single record (a huge block, or a flood
+/// of warnings between records) is ingested whole, as waiting would never help.
+fn ingestible_len(log_parser: &dyn MetricsParser, bytes: &[u8], done: bool) -> usize {
+    if done {
+        return bytes.len();
+    }
+    match log_parser.complete_len(bytes) {
+        0 if bytes.len() >= BATCH_BYTES => bytes.len(),
+        complete => complete,
+    }
+}
+
+/// Works out when each iteration was logged. Most libraries print the time elapsed
+/// since the start of training, which dates iterations correctly even when an old log
+/// is ingested long after the fact; iterations without it are dated now.
+fn wall_times(
+    points: &[metrics_parser::MetricPoint],
+    time_elapsed_key: Option<&str>,
+    started_at: Option<DateTime<Utc>>,
+) -> HashMap<i64, DateTime<Utc>> {
+    let now = Utc::now();
+    let mut wall_times = HashMap::new();
+    for point in points {
+        if Some(point.key.as_str()) == time_elapsed_key {
+            if let Some(started_at) = started_at {
+                let elapsed = chrono::Duration::milliseconds((point.value * 1000.0) as i64);
+                wall_times.insert(point.iteration, started_at + elapsed);
+            }
+        }
+    }
+    for point in points {
+        wall_times.entry(point.iteration).or_insert(now);
+    }
+    wall_times
+}
+
+/// Training progress as of the last record in a batch of points. Fields the batch says
+/// nothing about are `None` and keep their stored value.
+#[derive(Debug, Default)]
+struct Progress {
+    current_iteration: Option<i64>,
+    total_iterations: Option<i64>,
+    progress_pct: Option<f64>,
+    steps_per_sec: Option<f64>,
+    eta_seconds: Option<f64>,
+}
+
+fn progress(
+    points: &[metrics_parser::MetricPoint],
+    log_parser: &dyn MetricsParser,
+    known_total: Option<i64>,
+) -> Progress {
+    let latest = |key: Option<&str>| {
+        let key = key?;
+        points.iter().rev().find(|point| point.key == key).map(|point| point.value)
+    };
+    let Some(current) = points.iter().map(|point| point.iteration).max() else {
+        return Progress::default();
+    };
+    let total = latest(log_parser.total_iterations_key())
+        .map(|total| total as i64)
+        .or(known_total)
+        .filter(|total| *total > 0);
+
+    // Libraries that print no ETA get one extrapolated from the time taken so far.
+    let eta_seconds = latest(log_parser.eta_key()).or_else(|| {
+        let elapsed = latest(log_parser.time_elapsed_key())?;
+        let remaining = total? - current;
+        (current > 0).then(|| elapsed / current as f64 * remaining.max(0) as f64)
+    });
+
+    Progress {
+        current_iteration: Some(current),
+        total_iterations: total,
+        progress_pct: total.map(|total| (current as f64 / total as f64 * 100.0).min(100.0)),
+        steps_per_sec: latest(log_parser.fps_key()),
+        eta_seconds,
+    }
+}
+
+/// Drops all metrics stored for a task.
+pub async fn remove_metrics(db: &SqlitePool, task_id: &str) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM metrics WHERE task_id = ?")
+        .bind(task_id)
+        .execute(db)
+        .await?;
+    sqlx::query("DELETE FROM metrics_ingest_state WHERE task_id = ?")
+        .bind(task_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(iteration: usize, body: &str) -> String {
+        format!(
+            "{}\n Learning iteration {}/10\n\n Loss/learning_rate: 0.00{}\n{}",
+            "#".repeat(80),
+            iteration,
+            iteration + 1,
+            body
+        )
+    }
+
+    #[test]
+    fn ingests_blocks_larger_than_a_batch() {
+        let log_parser = LogFormat::RslRl.parser();
+        let warnings = "[Warning] contact sensor reports a NaN force\n".repeat(BATCH_BYTES / 40);
+        assert!(warnings.len() > BATCH_BYTES);
+        let log = [block(0, ""), block(1, &warnings), block(2, ""), block(3, "")].concat();
+
+        let mut offset = 0;
+        let mut state = ParserState::default();
+        let mut learning_rates = Vec::new();
+        while offset < log.len() {
+            let bytes = &log.as_bytes()[offset..(offset + BATCH_BYTES).min(log.len())];
+            let ingested_len = ingestible_len(log_parser, bytes, false);
+            if ingested_len == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&bytes[..ingested_len]);
+            let parsed = metrics_parser::parse_points(log_parser, &MetricRules::default(), &text, &mut state);
+            learning_rates.extend(
+                parsed
+                    .points
+                    .into_iter()
+                    .filter(|point| point.key == "Loss/learning_rate")
+                    .map(|point| (point.iteration, point.value)),
+            );
+            offset += ingested_len;
+        }
+
+        // Only the last block waits for the one after it.
+        assert_eq!(offset, log.len() - block(3, "").len());
+        assert_eq!(learning_rates, vec![(0, 0.001), (1, 0.002), (2, 0.003)]);
+    }
+
+    #[test]
+    fn ingests_everything_once_done() {
+        let log_parser = LogFormat::RslRl.parser();
+        let log = [block(0, ""), block(1, "")].concat();
+        assert_eq!(ingestible_len(log_parser, log.as_bytes(), false), block(0, "").len());
+        assert_eq!(ingestible_len(log_parser, log.as_bytes(), true), log.len());
+        assert_eq!(ingestible_len(log_parser, block(0, "").as_bytes(), false), 0);
+    }
+}
@@ -0,0 +1,307 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::terminal;
+
+mod rl_games;
+mod rsl_rl;
+mod rules;
+mod sb3;
+mod skrl;
+
+pub use rules::{MetricExtractor, MetricRules, DEFAULT_EXCLUDED_METRICS, DEFAULT_FIXED_METRICS};
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MetricsData {
+    pub latest_fixed_metrics: HashMap<String, String>,
+    pub historical_metrics: HashMap<String, Vec<(i64, f64)>>,
+}
+
+/// Where the parser stands in a log that is parsed piece by piece.
+#[derive(Debug, Clone, Default)]
+pub struct ParserState {
+    pub current_iteration: i64,
+}
+
+/// Reads the metrics an RL library prints to the console.
+///
+/// Logs are parsed incrementally: callers only hand over the part of the log that
+/// `complete_len` reports as complete, and keep the `ParserState` between calls.
+pub trait MetricsParser: Send + Sync {
+    /// Returns the length of the part of `log` made of complete records, i.e. the
+    /// offset of the first record that may still be extended.
+    fn complete_len(&self, log: &[u8]) -> usize;
+
+    /// Prepares raw log text for `parse`.
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        terminal::normalize(text)
+    }
+
+    /// Calls `emit` with the iteration, key and raw value of every metric in `content`,
+    /// which starts at a record boundary.
+    fn parse(&self, content: &str, state: &mut ParserState, emit: &mut dyn FnMut(i64, &str, &str));
+
+    /// Whether only the latest value of a metric is of interest (progress, timing and
+    /// the like) rather than its history.
+    fn is_fixed(&self, key: &str) -> bool;
+
+    /// Key of the metric holding the training time since the start of the run.
+    fn time_elapsed_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the metric holding the number of environment steps taken so far.
+    fn timesteps_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the metric holding the number of iterations the run will train for.
+    fn total_iterations_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the metric holding the estimated time left, as printed by the library.
+    fn eta_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the metric holding the mean episode reward.
+    fn reward_key(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Key of the metric holding the training throughput in steps per second.
+    fn fps_key(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// The console output formats that metrics can be parsed from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "log_format", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    #[default]
+    RslRl,
+    Skrl,
+    RlGames,
+    Sb3,
+}
+
+impl LogFormat {
+    /// Guesses the format from the training script a command runs, e.g.
+    /// `scripts/reinforcement_learning/skrl/train.py`.
+    pub fn detect(command: &str) -> Option<Self> {
+        let script_regex =
+            Regex::new(r"(?:reinforcement_learning|workflows)/(rsl_rl|skrl|rl_games|sb3)/").unwrap();
+        match &script_regex.captures(command)?[1] {
+            "rsl_rl" => Some(LogFormat::RslRl),
+            "skrl" => Some(LogFormat::Skrl),
+            "rl_games" => Some(LogFormat::RlGames),
+            "sb3" => Some(LogFormat::Sb3),
+            _ => None,
+        }
+    }
+
+    /// The format of a task's log: the one set explicitly, else the detected one, else
+    /// rsl_rl.
+    pub fn resolve(explicit: Option<LogFormat>, command: &str) -> Self {
+        explicit.or_else(|| Self::detect(command)).unwrap_or_default()
+    }
+
+    pub fn parser(self) -> &'static dyn MetricsParser {
+        match self {
+            LogFormat::RslRl => &rsl_rl::RslRlParser,
+            LogFormat::Skrl => &skrl::SkrlParser,
+            LogFormat::RlGames => &rl_games::RlGamesParser,
+            LogFormat::Sb3 => &sb3::Sb3Parser,
+        }
+    }
+}
+
+/// A single numeric metric value logged at an iteration.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricPoint {
+    pub iteration: i64,
+    pub key: String,
+    pub value: f64,
+}
+
+/// Parses a piece of a log that starts at a record boundary, continuing from `state`,
+/// into the shape the metrics API returns. The parser tests compare against it.
+#[cfg(test)]
+pub fn parse_chunk(
+    parser: &dyn MetricsParser,
+    rules: &MetricRules,
+    content: &str,
+    state: &mut ParserState,
+) -> MetricsData {
+    let mut latest_fixed_metrics = HashMap::new();
+    let mut historical_metrics: HashMap<String, Vec<(i64, f64)>> = HashMap::new();
+
+    rules.parse(parser, &parser.normalize(content), state, &mut |iteration, key, raw_value| {
+        if rules.is_fixed(parser, key) {
+            // Fixed metrics keep their text, e.g. for ETA which is not a number.
+            latest_fixed_metrics.insert(key.to_string(), raw_value.to_string());
+        } else if let Ok(value) = raw_value.parse::<f64>() {
+            historical_metrics
+                .entry(key.to_string())
+                .or_default()
+                .push((iteration, value));
+        }
+    });
+
+    MetricsData {
+        latest_fixed_metrics,
+        historical_metrics,
+    }
+}
+
+/// What the ingester keeps from a piece of a log.
+#[derive(Debug, Clone, Default)]
+pub struct ParsedPoints {
+    pub points: Vec<MetricPoint>,
+    /// The latest fixed metrics as printed, e.g. ETA as `01:02:10`.
+    pub latest_fixed_metrics: HashMap<String, String>,
+}
+
+/// Parses every numeric metric of a piece of a log, fixed metrics included. Durations
+/// such as `Time elapsed` are converted to seconds, and values with a unit such as
+/// `39521 steps/s (collection: 2.234s, ...)` to their leading number.
+pub fn parse_points(
+    parser: &dyn MetricsParser,
+    rules: &MetricRules,
+    content: &str,
+    state: &mut ParserState,
+) -> ParsedPoints {
+    let mut parsed = ParsedPoints::default();
+    rules.parse(parser, &parser.normalize(content), state, &mut |iteration, key, raw_value| {
+        if rules.is_fixed(parser, key) {
+            parsed
+                .latest_fixed_metrics
+                .insert(key.to_string(), raw_value.to_string());
+        }
+        let value = raw_value
+            .parse::<f64>()
+            .ok()
+            .or_else(|| parse_duration_secs(raw_value))
+            .or_else(|| leading_number(raw_value));
+        if let Some(value) = value {
+            parsed.points.push(MetricPoint {
+                iteration,
+                key: key.to_string(),
+                value,
+            });
+        }
+    });
+    parsed
+}
+
+/// Parses the durations the libraries print, `HH:MM:SS` or seconds such as `123.4s`.
+fn parse_duration_secs(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if let Some(seconds) = value.strip_suffix('s') {
+        return seconds.trim().parse().ok();
+    }
+    if !value.contains(':') {
+        return None;
+    }
+    let mut total = 0.0;
+    for part in value.split(':') {
+        total = total * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Some(total)
+}
+
+/// The number a value starts with, when it is followed by a unit or a remark.
+fn leading_number(value: &str) -> Option<f64> {
+    let (number, _) = value.trim().split_once(char::is_whitespace)?;
+    number.parse().ok()
+}
+
+/// Offset right after the last complete line of `log`.
+fn complete_lines_len(log: &[u8]) -> usize {
+    log.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURES: [(LogFormat, &str); 4] = [
+        (LogFormat::RslRl, include_str!("../../tests/fixtures/metrics/rsl_rl.log")),
+        (LogFormat::Skrl, include_str!("../../tests/fixtures/metrics/skrl.log")),
+        (LogFormat::RlGames, include_str!("../../tests/fixtures/metrics/rl_games.log")),
+        (LogFormat::Sb3, include_str!("../../tests/fixtures/metrics/sb3.log")),
+    ];
+
+    #[test]
+    fn detects_format_from_training_script() {
+        let cases = [
+            ("python scripts/reinforcement_learning/rsl_rl/train.py --task Isaac-Ant-v0", Some(LogFormat::RslRl)),
+            ("./isaaclab.sh -p scripts/reinforcement_learning/skrl/train.py --task X", Some(LogFormat::Skrl)),
+            ("python scripts/reinforcement_learning/rl_games/train.py --headless", Some(LogFormat::RlGames)),
+            ("python source/standalone/workflows/sb3/train.py", Some(LogFormat::Sb3)),
+            ("python my_script.py", None),
+        ];
+        for (command, expected) in cases {
+            assert_eq!(LogFormat::detect(command), expected, "{}", command);
+        }
+    }
+
+    /// Parsing a log in pieces, as the ingester does, must give the same result as
+    /// parsing it at once.
+    #[test]
+    fn incremental_parsing_matches_full_parse() {
+        for (format, log) in FIXTURES {
+            let parser = format.parser();
+            let full = parse_chunk(parser, &MetricRules::default(), log, &mut ParserState::default());
+
+            let mut incremental = MetricsData::default();
+            let mut state = ParserState::default();
+            let mut offset = 0;
+            for end in (1..=log.len()).step_by(97).chain([log.len()]) {
+                if !log.is_char_boundary(end) {
+                    continue;
+                }
+                let pending = &log.as_bytes()[offset..end];
+                let complete = if end == log.len() {
+                    pending.len()
+                } else {
+                    parser.complete_len(pending)
+                };
+                let text = std::str::from_utf8(&pending[..complete]).unwrap();
+                let later = parse_chunk(parser, &MetricRules::default(), text, &mut state);
+                incremental.latest_fixed_metrics.extend(later.latest_fixed_metrics);
+                for (key, points) in later.historical_metrics {
+                    incremental.historical_metrics.entry(key).or_default().extend(points);
+                }
+                offset += complete;
+            }
+
+            assert_eq!(incremental.latest_fixed_metrics, full.latest_fixed_metrics, "{:?}", format);
+            assert_eq!(incremental.historical_metrics, full.historical_metrics, "{:?}", format);
+        }
+    }
+
+    /// The ingester takes the printed fixed metrics from the same pass as the points.
+    #[test]
+    fn parsed_points_keep_fixed_metrics_as_printed() {
+        for (format, log) in FIXTURES {
+            let parser = format.parser();
+            let chunk = parse_chunk(parser, &MetricRules::default(), log, &mut ParserState::default());
+            let parsed = parse_points(parser, &MetricRules::default(), log, &mut ParserState::default());
+            assert_eq!(parsed.latest_fixed_metrics, chunk.latest_fixed_metrics, "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration_secs("00:01:05"), Some(65.0));
+        assert_eq!(parse_duration_secs("2.5s"), Some(2.5));
+        assert_eq!(parse_duration_secs("06:40"), Some(400.0));
+        assert_eq!(parse_duration_secs("fast"), None);
+    }
+}
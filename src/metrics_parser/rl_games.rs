@@ -0,0 +1,89 @@
+use regex::Regex;
+
+use super::{complete_lines_len, MetricsParser, ParserState};
+
+/// rl_games prints one line per epoch with its throughput,
+///
+/// ```text
+/// fps step: 31250 fps step and policy inference: 28571 fps total: 22222 epoch: 1/500 frames: 0
+/// ```
+///
+/// and a line whenever a checkpoint with a new best mean reward is saved.
+pub struct RlGamesParser;
+
+impl MetricsParser for RlGamesParser {
+    fn complete_len(&self, log: &[u8]) -> usize {
+        complete_lines_len(log)
+    }
+
+    fn parse(&self, content: &str, state: &mut ParserState, emit: &mut dyn FnMut(i64, &str, &str)) {
+        let epoch_regex = Regex::new(
+            r"fps step:\s*([\d.]+)\s+fps step and policy inference:\s*([\d.]+)\s+fps total:\s*([\d.]+)\s+epoch:\s*(\d+)(?:/(\d+))?(?:\s+frames:\s*(\d+))?",
+        )
+        .unwrap();
+        let best_reward_regex =
+            Regex::new(r"saving next best rewards:\s*\[?\s*(-?[\d.]+(?:e[+-]?\d+)?)").unwrap();
+
+        for line in content.lines() {
+            if let Some(captures) = epoch_regex.captures(line) {
+                let Ok(epoch) = captures[4].parse::<i64>() else {
+                    continue;
+                };
+                state.current_iteration = epoch;
+                emit(epoch, "fps step", &captures[1]);
+                emit(epoch, "fps step and policy inference", &captures[2]);
+                emit(epoch, "fps total", &captures[3]);
+                emit(epoch, "Epoch", &captures[4]);
+                if let Some(max_epochs) = captures.get(5) {
+                    emit(epoch, "Max epochs", max_epochs.as_str());
+                }
+                if let Some(frames) = captures.get(6) {
+                    emit(epoch, "Frames", frames.as_str());
+                }
+            } else if let Some(captures) = best_reward_regex.captures(line) {
+                emit(state.current_iteration, "Best reward", &captures[1]);
+            }
+        }
+    }
+
+    fn is_fixed(&self, key: &str) -> bool {
+        matches!(key, "Epoch" | "Max epochs" | "Frames")
+    }
+
+    fn timesteps_key(&self) -> Option<&'static str> {
+        Some("Frames")
+    }
+
+    fn total_iterations_key(&self) -> Option<&'static str> {
+        Some("Max epochs")
+    }
+
+    fn fps_key(&self) -> Option<&'static str> {
+        Some("fps total")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics_parser::{parse_chunk, MetricRules};
+
+    const LOG: &str = include_str!("../../tests/fixtures/metrics/rl_games.log");
+
+    #[test]
+    fn parses_epoch_lines() {
+        let metrics = parse_chunk(&RlGamesParser, &MetricRules::default(), LOG, &mut ParserState::default());
+
+        assert_eq!(
+            metrics.historical_metrics["fps total"],
+            vec![(1, 22222.0), (2, 23810.0), (3, 24015.0)]
+        );
+        assert_eq!(
+            metrics.historical_metrics["Best reward"],
+            vec![(1, 0.512), (3, 1.734)]
+        );
+        assert_eq!(metrics.latest_fixed_metrics["Epoch"], "3");
+        assert_eq!(metrics.latest_fixed_metrics["Max epochs"], "500");
+        assert_eq!(metrics.latest_fixed_metrics["Frames"], "65536");
+    }
+}
@@ -0,0 +1,114 @@
+use regex::Regex;
+
+use super::{MetricsParser, ParserState};
+
+/// The line rsl_rl prints at the start of every iteration's block of metrics.
+const BLOCK_SEPARATOR: &str = "################################################################################";
+
+/// rsl_rl's `OnPolicyRunner` prints one block of `key: value` lines per iteration,
+/// each starting with a line of `#` and a `Learning iteration N/M` header.
+pub struct RslRlParser;
+
+impl MetricsParser for RslRlParser {
+    /// A block is only complete once the separator of the next one has been written.
+    fn complete_len(&self, log: &[u8]) -> usize {
+        log.windows(BLOCK_SEPARATOR.len())
+            .rposition(|window| window == BLOCK_SEPARATOR.as_bytes())
+            .unwrap_or(0)
+    }
+
+    fn parse(&self, content: &str, state: &mut ParserState, emit: &mut dyn FnMut(i64, &str, &str)) {
+        let iteration_regex = Regex::new(r"Learning iteration (\d+)/(\d+)").unwrap();
+        let metric_regex = Regex::new(r"^\s*([^:]+):\s+(.+)").unwrap();
+
+        for block in content.split(BLOCK_SEPARATOR).filter(|s| !s.trim().is_empty()) {
+            if let Some(captures) = iteration_regex.captures(block) {
+                if let Ok(iteration_num) = captures[1].parse::<i64>() {
+                    state.current_iteration = iteration_num;
+                    emit(iteration_num, "Max iterations", &captures[2]);
+                }
+            }
+
+            for line in block.lines() {
+                if let Some(captures) = metric_regex.captures(line.trim()) {
+                    emit(state.current_iteration, captures[1].trim(), &captures[2]);
+                }
+            }
+        }
+    }
+
+    /// Which of rsl_rl's lines are fixed is configured, see `MetricRules`.
+    fn is_fixed(&self, _key: &str) -> bool {
+        false
+    }
+
+    fn timesteps_key(&self) -> Option<&'static str> {
+        Some("Total timesteps")
+    }
+
+    fn time_elapsed_key(&self) -> Option<&'static str> {
+        Some("Time elapsed")
+    }
+
+    fn total_iterations_key(&self) -> Option<&'static str> {
+        Some("Max iterations")
+    }
+
+    fn eta_key(&self) -> Option<&'static str> {
+        Some("ETA")
+    }
+
+    fn reward_key(&self) -> Option<&'static str> {
+        Some("Mean reward")
+    }
+
+    fn fps_key(&self) -> Option<&'static str> {
+        Some("Computation")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics_parser::{parse_chunk, MetricRules};
+
+    const LOG: &str = include_str!("../../tests/fixtures/metrics/rsl_rl.log");
+
+    #[test]
+    fn parses_iteration_blocks() {
+        let metrics = parse_chunk(&RslRlParser, &MetricRules::default(), LOG, &mut ParserState::default());
+
+        assert_eq!(
+            metrics.historical_metrics["Episode_Reward/track_lin_vel_xy_exp"],
+            vec![(0, 0.0080), (1, 0.0123), (2, 0.0219)]
+        );
+        assert_eq!(
+            metrics.historical_metrics["Loss/learning_rate"],
+            vec![(0, 0.001), (1, 0.001), (2, 0.0005)]
+        );
+        assert_eq!(metrics.latest_fixed_metrics["Mean reward"], "-0.41");
+        assert_eq!(metrics.latest_fixed_metrics["Total timesteps"], "294912");
+        assert_eq!(metrics.latest_fixed_metrics["ETA"], "01:02:10");
+        assert_eq!(metrics.latest_fixed_metrics["Max iterations"], "1500");
+        assert!(metrics
+            .latest_fixed_metrics
+            .get("Computation")
+            .is_some_and(|value| value.starts_with("40211 steps/s")));
+    }
+
+    #[test]
+    fn skips_environment_settings() {
+        let metrics = parse_chunk(&RslRlParser, &MetricRules::default(), LOG, &mut ParserState::default());
+        assert!(!metrics
+            .historical_metrics
+            .keys()
+            .any(|key| key.to_lowercase().contains("step-size")));
+    }
+
+    #[test]
+    fn last_block_is_incomplete() {
+        let complete = RslRlParser.complete_len(LOG.as_bytes());
+        assert!(LOG[complete..].contains("Learning iteration 2/1500"));
+        assert!(!LOG[..complete].contains("Learning iteration 2/1500"));
+    }
+}
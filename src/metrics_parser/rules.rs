@@ -0,0 +1,207 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use super::{MetricsParser, ParserState};
+use crate::config::MetricsConfig;
+
+/// Metrics that only show their latest value by default: rsl_rl's progress, timing
+/// and loss summary lines.
+pub const DEFAULT_FIXED_METRICS: &[&str] = &[
+    "Max iterations",
+    "Computation",
+    "Mean action noise std",
+    "Mean value_function loss",
+    "Mean surrogate loss",
+    "Mean entropy loss",
+    "Mean reward",
+    "Mean episode length",
+    "Total timesteps",
+    "Iteration time",
+    "Time elapsed",
+    "ETA",
+];
+
+/// Environment settings IsaacLab prints as `key: value` lines before training starts.
+pub const DEFAULT_EXCLUDED_METRICS: &[&str] = &[
+    "physics step-size",
+    "rendering step-size",
+    "environment step-size",
+    "active action terms",
+    "environment seed",
+    "environment spacing",
+    "setting seed",
+    "number of environments",
+];
+
+/// A user-defined metric read from every log line matching `pattern`, whose first
+/// capture group holds the value, e.g. `curriculum level: (\d+)`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricExtractor {
+    pub key: String,
+    pub pattern: String,
+}
+
+impl MetricExtractor {
+    /// Compiles the pattern, rejecting patterns that capture nothing.
+    pub fn compile(&self) -> Result<Regex, String> {
+        let regex = Regex::new(&self.pattern)
+            .map_err(|e| format!("Invalid pattern for metric '{}': {}", self.key, e))?;
+        if regex.captures_len() < 2 {
+            return Err(format!(
+                "Pattern for metric '{}' needs a capture group for the value",
+                self.key
+            ));
+        }
+        Ok(regex)
+    }
+}
+
+/// Which metrics are reported as fixed or dropped, on top of what the parser of the
+/// log format decides, and the extra metrics a task reads with its own patterns.
+#[derive(Debug, Clone)]
+pub struct MetricRules {
+    fixed: Vec<String>,
+    excluded: Vec<String>,
+    extractors: Vec<(String, Regex)>,
+}
+
+impl Default for MetricRules {
+    fn default() -> Self {
+        Self {
+            fixed: DEFAULT_FIXED_METRICS.iter().map(|key| key.to_string()).collect(),
+            excluded: DEFAULT_EXCLUDED_METRICS.iter().map(|key| key.to_string()).collect(),
+            extractors: Vec::new(),
+        }
+    }
+}
+
+impl PartialEq for MetricRules {
+    fn eq(&self, other: &Self) -> bool {
+        self.fixed == other.fixed
+            && self.excluded == other.excluded
+            && self.extractors.len() == other.extractors.len()
+            && self
+                .extractors
+                .iter()
+                .zip(&other.extractors)
+                .all(|((a, a_regex), (b, b_regex))| a == b && a_regex.as_str() == b_regex.as_str())
+    }
+}
+
+impl MetricRules {
+    /// Rules from the configured lists and a task's extractors. Extractors with an
+    /// invalid pattern are skipped; they are rejected when the task is created.
+    pub fn new(config: &MetricsConfig, extractors: &[MetricExtractor]) -> Self {
+        Self {
+            fixed: config.fixed_metrics.clone(),
+            excluded: config
+                .excluded_metrics
+                .iter()
+                .map(|key| key.to_lowercase())
+                .collect(),
+            extractors: extractors
+                .iter()
+                .filter_map(|extractor| Some((extractor.key.clone(), extractor.compile().ok()?)))
+                .collect(),
+        }
+    }
+
+    pub fn is_fixed(&self, parser: &dyn MetricsParser, key: &str) -> bool {
+        parser.is_fixed(key) || self.fixed.iter().any(|fixed| fixed == key)
+    }
+
+    /// Excluded entries match any key containing them, ignoring case.
+    pub fn is_excluded(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        self.excluded.iter().any(|excluded| key.contains(excluded.as_str()))
+    }
+
+    /// Runs `parser` over `content` and the extractors over its lines, passing every
+    /// metric that is not excluded to `emit`.
+    ///
+    /// A value found by an extractor is logged at the iteration the parser had reached
+    /// at its line. That iteration is worked out on a copy of the parser state, so
+    /// splitting the content at matching lines never affects the parsed metrics.
+    pub(super) fn parse(
+        &self,
+        parser: &dyn MetricsParser,
+        content: &str,
+        state: &mut ParserState,
+        emit: &mut dyn FnMut(i64, &str, &str),
+    ) {
+        let mut probe = state.clone();
+        parser.parse(content, state, &mut |iteration, key, raw_value| {
+            if !self.is_excluded(key) {
+                emit(iteration, key, raw_value);
+            }
+        });
+        if self.extractors.is_empty() {
+            return;
+        }
+
+        let mut probed_to = 0;
+        let mut line_start = 0;
+        for line in content.split_inclusive('\n') {
+            for (key, regex) in &self.extractors {
+                let Some(value) = regex.captures(line).and_then(|captures| captures.get(1)) else {
+                    continue;
+                };
+                if probed_to < line_start {
+                    parser.parse(&content[probed_to..line_start], &mut probe, &mut |_, _, _| {});
+                    probed_to = line_start;
+                }
+                emit(probe.current_iteration, key, value.as_str().trim());
+            }
+            line_start += line.len();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics_parser::{parse_chunk, LogFormat};
+
+    const LOG: &str = include_str!("../../tests/fixtures/metrics/rsl_rl.log");
+
+    #[test]
+    fn extractors_add_series_at_the_current_iteration() {
+        let log = LOG
+            .replacen("Learning iteration 1/1500", "curriculum level: 3\nLearning iteration 1/1500", 1)
+            .replacen("Learning iteration 2/1500", "curriculum level: 4\nLearning iteration 2/1500", 1);
+        let config = MetricsConfig {
+            auto_refresh_interval_secs: 30,
+            fixed_metrics: vec!["Loss/learning_rate".to_string()],
+            excluded_metrics: vec!["episode_reward".to_string()],
+        };
+        let rules = MetricRules::new(
+            &config,
+            &[MetricExtractor {
+                key: "Curriculum level".to_string(),
+                pattern: r"curriculum level: (\d+)".to_string(),
+            }],
+        );
+
+        let metrics = parse_chunk(LogFormat::RslRl.parser(), &rules, &log, &mut ParserState::default());
+
+        // The level printed before a block belongs to the iteration that just ended.
+        assert_eq!(
+            metrics.historical_metrics["Curriculum level"],
+            vec![(0, 3.0), (1, 4.0)]
+        );
+        assert_eq!(metrics.latest_fixed_metrics["Loss/learning_rate"], "0.0005");
+        assert!(!metrics
+            .historical_metrics
+            .keys()
+            .any(|key| key.starts_with("Episode_Reward")));
+    }
+
+    #[test]
+    fn rejects_patterns_without_capture_group() {
+        let extractor = MetricExtractor {
+            key: "level".to_string(),
+            pattern: r"curriculum level: \d+".to_string(),
+        };
+        assert!(extractor.compile().is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use regex::Regex;
+
+use super::{MetricsParser, ParserState};
+
+/// Stable-Baselines3 prints its logger's values as a table after every rollout:
+///
+/// ```text
+/// -----------------------------------------
+/// | rollout/                |             |
+/// |    ep_rew_mean          | 35.2        |
+/// | time/                   |             |
+/// |    iterations           | 2           |
+/// -----------------------------------------
+/// ```
+///
+/// Keys are prefixed with their section, e.g. `rollout/ep_rew_mean`.
+pub struct Sb3Parser;
+
+fn is_rule(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.bytes().all(|b| b == b'-')
+}
+
+fn is_row(line: &str) -> bool {
+    line.trim_start().starts_with('|')
+}
+
+impl MetricsParser for Sb3Parser {
+    /// A table is complete once its closing rule has been written.
+    fn complete_len(&self, log: &[u8]) -> usize {
+        let mut complete = 0;
+        let mut offset = 0;
+        let mut previous_is_row = false;
+        for line in log.split_inclusive(|&b| b == b'\n') {
+            offset += line.len();
+            if !line.ends_with(b"\n") {
+                break;
+            }
+            let line = String::from_utf8_lossy(line);
+            if is_rule(&line) && previous_is_row {
+                complete = offset;
+            }
+            if !line.trim().is_empty() {
+                previous_is_row = is_row(&line);
+            }
+        }
+        complete
+    }
+
+    fn parse(&self, content: &str, state: &mut ParserState, emit: &mut dyn FnMut(i64, &str, &str)) {
+        let row_regex = Regex::new(r"^\|\s*([^|]*?)\s*\|\s*([^|]*?)\s*\|$").unwrap();
+
+        let mut section = String::new();
+        let mut rows: Vec<(String, String)> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if is_rule(line) {
+                if rows.is_empty() {
+                    continue;
+                }
+                // Closing rule: the table is complete.
+                let value_of = |key: &str| {
+                    rows.iter()
+                        .find(|(k, _)| k == key)
+                        .and_then(|(_, v)| v.parse::<i64>().ok())
+                };
+                let iteration = value_of("time/iterations")
+                    .or_else(|| value_of("time/total_timesteps"))
+                    .unwrap_or(state.current_iteration + 1);
+                state.current_iteration = iteration;
+                for (key, value) in rows.drain(..) {
+                    emit(iteration, &key, &value);
+                }
+                section.clear();
+            } else if let Some(captures) = row_regex.captures(line) {
+                let (name, value) = (&captures[1], &captures[2]);
+                if value.is_empty() {
+                    section = name.to_string();
+                } else {
+                    rows.push((format!("{}{}", section, name), value.to_string()));
+                }
+            }
+        }
+    }
+
+    fn is_fixed(&self, key: &str) -> bool {
+        key.starts_with("time/")
+    }
+
+    fn timesteps_key(&self) -> Option<&'static str> {
+        Some("time/total_timesteps")
+    }
+
+    fn time_elapsed_key(&self) -> Option<&'static str> {
+        Some("time/time_elapsed")
+    }
+
+    fn reward_key(&self) -> Option<&'static str> {
+        Some("rollout/ep_rew_mean")
+    }
+
+    fn fps_key(&self) -> Option<&'static str> {
+        Some("time/fps")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics_parser::{parse_chunk, MetricRules};
+
+    const LOG: &str = include_str!("../../tests/fixtures/metrics/sb3.log");
+
+    #[test]
+    fn parses_tables() {
+        let metrics = parse_chunk(&Sb3Parser, &MetricRules::default(), LOG, &mut ParserState::default());
+
+        assert_eq!(
+            metrics.historical_metrics["rollout/ep_rew_mean"],
+            vec![(1, 22.4), (2, 35.2), (3, -1.2e3)]
+        );
+        assert_eq!(
+            metrics.historical_metrics["train/approx_kl"],
+            vec![(2, 0.011812365), (3, 0.0094)]
+        );
+        assert_eq!(metrics.latest_fixed_metrics["time/total_timesteps"], "49152");
+        assert!(!metrics.historical_metrics.contains_key("time/fps"));
+    }
+
+    #[test]
+    fn table_is_complete_after_closing_rule() {
+        let complete = Sb3Parser.complete_len(LOG.as_bytes());
+        assert_eq!(complete, LOG.len());
+
+        let partial = &LOG[..LOG.len() - 20];
+        let complete = Sb3Parser.complete_len(partial.as_bytes());
+        assert!(partial[..complete].ends_with("-\n"));
+        assert!(partial[complete..].contains("49152"));
+    }
+}
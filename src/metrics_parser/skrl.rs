@@ -0,0 +1,121 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use super::{MetricsParser, ParserState};
+use crate::terminal;
+
+/// skrl's trainers only report progress on the console, as a tqdm bar over timesteps:
+///
+/// ```text
+///  2%|▏         | 96/4800 [00:02<01:47, 43.67it/s]
+/// ```
+///
+/// Training metrics themselves only go to TensorBoard.
+pub struct SkrlParser;
+
+const ITERATIONS_PER_SECOND: &str = "Iterations per second";
+
+impl MetricsParser for SkrlParser {
+    /// tqdm starts every redraw with `\r`, so a bar is complete once the next one begins.
+    fn complete_len(&self, log: &[u8]) -> usize {
+        log.iter()
+            .rposition(|&b| b == b'\r' || b == b'\n')
+            .unwrap_or(0)
+    }
+
+    /// Keeps every redraw of the progress bar as a line of its own instead of
+    /// collapsing them into the last one.
+    fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !text.contains('\r') {
+            return terminal::normalize(text);
+        }
+        Cow::Owned(terminal::normalize(&text.replace('\r', "\n")).into_owned())
+    }
+
+    fn parse(&self, content: &str, state: &mut ParserState, emit: &mut dyn FnMut(i64, &str, &str)) {
+        let progress_regex = Regex::new(
+            r"\|\s*(\d+)/(\d+)\s*\[([\d:]+)<([\d:?]+),\s*([\d.?]+)(it/s|s/it)\]",
+        )
+        .unwrap();
+
+        for line in content.lines() {
+            let Some(captures) = progress_regex.captures(line) else {
+                continue;
+            };
+            let Ok(timestep) = captures[1].parse::<i64>() else {
+                continue;
+            };
+            state.current_iteration = timestep;
+            emit(timestep, "Timestep", &captures[1]);
+            emit(timestep, "Total timesteps", &captures[2]);
+            emit(timestep, "Time elapsed", &captures[3]);
+            if &captures[4] != "?" {
+                emit(timestep, "ETA", &captures[4]);
+            }
+            if let Ok(rate) = captures[5].parse::<f64>() {
+                let rate = if &captures[6] == "s/it" { 1.0 / rate } else { rate };
+                emit(timestep, ITERATIONS_PER_SECOND, &rate.to_string());
+            }
+        }
+    }
+
+    fn is_fixed(&self, key: &str) -> bool {
+        key != ITERATIONS_PER_SECOND
+    }
+
+    fn timesteps_key(&self) -> Option<&'static str> {
+        Some("Timestep")
+    }
+
+    fn time_elapsed_key(&self) -> Option<&'static str> {
+        Some("Time elapsed")
+    }
+
+    fn total_iterations_key(&self) -> Option<&'static str> {
+        Some("Total timesteps")
+    }
+
+    fn eta_key(&self) -> Option<&'static str> {
+        Some("ETA")
+    }
+
+    /// tqdm counts timesteps, so its rate is the number of timesteps per second.
+    fn fps_key(&self) -> Option<&'static str> {
+        Some(ITERATIONS_PER_SECOND)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics_parser::{parse_chunk, parse_points, MetricRules};
+
+    const LOG: &str = include_str!("../../tests/fixtures/metrics/skrl.log");
+
+    #[test]
+    fn parses_every_progress_bar_redraw() {
+        let metrics = parse_chunk(&SkrlParser, &MetricRules::default(), LOG, &mut ParserState::default());
+
+        assert_eq!(
+            metrics.historical_metrics[ITERATIONS_PER_SECOND],
+            vec![(96, 43.67), (192, 44.12), (288, 44.3), (4800, 45.01)]
+        );
+        assert_eq!(metrics.latest_fixed_metrics["Timestep"], "4800");
+        assert_eq!(metrics.latest_fixed_metrics["Total timesteps"], "4800");
+        assert_eq!(metrics.latest_fixed_metrics["Time elapsed"], "01:46");
+    }
+
+    #[test]
+    fn converts_seconds_per_iteration() {
+        let log = "\r  0%|          | 1/4800 [00:04<5:20:00,  4.00s/it]\n";
+        let points = parse_points(&SkrlParser, &MetricRules::default(), log, &mut ParserState::default()).points;
+        let rate = points
+            .iter()
+            .find(|point| point.key == ITERATIONS_PER_SECOND)
+            .unwrap();
+        assert_eq!(rate.value, 0.25);
+        let eta = points.iter().find(|point| point.key == "ETA").unwrap();
+        assert_eq!(eta.value, 19200.0);
+    }
+}
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
-use sqlx::SqlitePool;
+use sqlx::{types::Json, SqlitePool};
 use std::{collections::HashMap, sync::Arc};
-use tokio::{process::Child, sync::{Mutex, RwLock}};
+use tokio::sync::{Mutex, RwLock};
 
-use crate::config;
+use crate::{
+    config, metrics::Metrics, metrics_parser::{LogFormat, MetricExtractor, MetricRules},
+    notifications::NotificationService, tensorboard::TensorboardManager,
+};
 
 // --- Data Structures ---
 
@@ -21,6 +24,76 @@ pub struct Task {
     pub started_at: Option<chrono::DateTime<chrono::Utc>>,
     pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
     pub log_path: Option<String>,
+    #[sqlx(default)]
+    pub tensorboard_port: Option<i64>,
+    #[sqlx(default)]
+    pub stalled_at: Option<chrono::DateTime<chrono::Utc>>,
+    #[sqlx(default)]
+    pub retry_count: i64,
+    #[sqlx(default)]
+    pub exit_code: Option<i64>,
+    /// Console output format of the training script, detected from the command when
+    /// the task is created unless given explicitly.
+    #[sqlx(default)]
+    pub log_format: Option<LogFormat>,
+    /// Training progress, kept up to date from the log by the metrics ingester.
+    #[sqlx(default)]
+    pub current_iteration: Option<i64>,
+    #[sqlx(default)]
+    pub total_iterations: Option<i64>,
+    #[sqlx(default)]
+    pub progress_pct: Option<f64>,
+    #[sqlx(default)]
+    pub steps_per_sec: Option<f64>,
+    #[sqlx(default)]
+    pub eta_seconds: Option<f64>,
+    /// Extra metrics read from the log with the task's own patterns.
+    #[sqlx(default)]
+    pub metric_extractors: Option<Json<Vec<MetricExtractor>>>,
+    /// Why the task failed, classified from the end of its log.
+    #[sqlx(default)]
+    pub failure_category: Option<String>,
+    #[sqlx(default)]
+    pub failure_excerpt: Option<String>,
+    /// Directory the training run writes its logs and checkpoints to, detected while
+    /// the task runs.
+    #[sqlx(default)]
+    pub run_dir: Option<String>,
+    /// Every how many new checkpoints an evaluation task is queued for the task.
+    #[sqlx(default)]
+    pub eval_every: Option<i64>,
+    /// For evaluation tasks, the task and checkpoint iteration that are evaluated.
+    #[sqlx(default)]
+    pub eval_source_task_id: Option<String>,
+    #[sqlx(default)]
+    pub eval_iteration: Option<i64>,
+    /// Hash of the code in the working directory when the task started.
+    #[sqlx(default)]
+    pub code_hash: Option<String>,
+    /// Latest value of each fixed metric, as printed in the log.
+    #[sqlx(default)]
+    pub latest_fixed_metrics: Option<Json<HashMap<String, String>>>,
+}
+
+impl Task {
+    /// The format to parse the task's log with.
+    pub fn log_format(&self) -> LogFormat {
+        LogFormat::resolve(self.log_format, &self.command)
+    }
+
+    /// The directory the task runs in.
+    pub fn resolved_working_dir(&self, config: &config::TaskConfig) -> std::path::PathBuf {
+        match &self.working_dir {
+            Some(dir) => std::path::PathBuf::from(dir),
+            None => config.working_directory.clone(),
+        }
+    }
+
+    /// The rules to classify the task's metrics with.
+    pub fn metric_rules(&self, config: &config::MetricsConfig) -> MetricRules {
+        let extractors = self.metric_extractors.as_ref().map_or(&[][..], |json| &json.0);
+        MetricRules::new(config, extractors)
+    }
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, PartialEq, Eq)]
@@ -39,6 +112,47 @@ pub struct CreateTaskRequest {
     pub command: String,
     pub conda_env: Option<String>,
     pub working_dir: Option<String>,
+    pub log_format: Option<LogFormat>,
+    #[serde(default)]
+    pub metric_extractors: Vec<MetricExtractor>,
+    /// Queue an evaluation of every N-th checkpoint the task saves.
+    pub eval_every: Option<i64>,
+}
+
+/// A checkpoint promoted to a named model version, with where it came from.
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct RegisteredModel {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub task_id: String,
+    /// The checkpoint's path relative to the task's run directory.
+    pub checkpoint: String,
+    pub iteration: Option<i64>,
+    pub command: String,
+    /// Hash of the code in the task's working directory when the task started.
+    pub code_hash: Option<String>,
+    pub notes: Option<String>,
+    /// Metrics of the task at the checkpoint's iteration.
+    pub metrics: Json<HashMap<String, f64>>,
+    pub file_path: String,
+    pub size: i64,
+    pub sha256: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PromoteModelRequest {
+    pub task_id: String,
+    pub checkpoint: String,
+    pub name: String,
+    pub version: String,
+    pub notes: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListModelsRequest {
+    pub name: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -51,6 +165,20 @@ pub struct SyncRequest {
     pub remote_path: Option<String>,
 }
 
+/// A file to upload in chunks, with its path relative to the sync target.
+#[derive(Debug, Deserialize)]
+pub struct StartChunkedUploadRequest {
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// Files to remove from the sync target, relative to it as in the manifest.
+#[derive(Debug, Deserialize)]
+pub struct SyncDeleteRequest {
+    pub paths: Vec<String>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct FileInfo {
     pub name: String,
@@ -81,12 +209,38 @@ pub struct AppState {
     pub db: SqlitePool,
     pub tasks: Arc<RwLock<HashMap<String, TaskInfo>>>,
     pub queue: Arc<Mutex<Vec<String>>>,
-    pub current_task: Arc<Mutex<Option<String>>>,
     pub config: Arc<RwLock<config::Config>>,
+    pub notifications: NotificationService,
+    pub tensorboard: TensorboardManager,
+    pub metrics: Metrics,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State backed by a fresh in-memory database with every migration applied.
+    pub async fn for_tests(config: config::Config) -> Self {
+        // Every connection to `:memory:` opens its own database, so keep exactly one.
+        let db = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!("./migrations").run(&db).await.unwrap();
+        Self {
+            db,
+            tasks: Arc::new(RwLock::new(HashMap::new())),
+            queue: Arc::new(Mutex::new(Vec::new())),
+            config: Arc::new(RwLock::new(config)),
+            notifications: NotificationService::new(),
+            tensorboard: TensorboardManager::default(),
+            metrics: Metrics::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TaskInfo {
     pub task: Task,
-    pub process: Option<Arc<Mutex<Child>>>,
 }
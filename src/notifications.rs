@@ -0,0 +1,123 @@
+// src/notifications.rs
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum NotificationType {
+    TaskCreated,
+    TaskStarted,
+    TaskCompleted,
+    TaskFailed,
+    TaskStopped,
+    TaskStalled,
+    SyncCompleted,
+    SystemError,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: String,
+    pub notification_type: NotificationType,
+    pub title: String,
+    pub message: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub task_id: Option<String>,
+}
+
+impl Notification {
+    pub fn new(
+        notification_type: NotificationType,
+        title: String,
+        message: String,
+        task_id: Option<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            notification_type,
+            title,
+            message,
+            timestamp: chrono::Utc::now(),
+            task_id,
+        }
+    }
+
+    pub fn task_created(task_name: &str, task_id: &str) -> Self {
+        Self::new(
+            NotificationType::TaskCreated,
+            "任务已创建".to_string(),
+            format!("任务 '{}' 已添加到队列", task_name),
+            Some(task_id.to_string()),
+        )
+    }
+
+    pub fn task_started(task_name: &str, task_id: &str) -> Self {
+        Self::new(
+            NotificationType::TaskStarted,
+            "任务已开始".to_string(),
+            format!("任务 '{}' 开始执行", task_name),
+            Some(task_id.to_string()),
+        )
+    }
+
+    pub fn task_completed(task_name: &str, task_id: &str) -> Self {
+        Self::new(
+            NotificationType::TaskCompleted,
+            "任务已完成".to_string(),
+            format!("任务 '{}' 执行完成", task_name),
+            Some(task_id.to_string()),
+        )
+    }
+
+    pub fn task_failed(task_name: &str, task_id: &str, error: &str) -> Self {
+        Self::new(
+            NotificationType::TaskFailed,
+            "任务执行失败".to_string(),
+            format!("任务 '{}' 执行失败: {}", task_name, error),
+            Some(task_id.to_string()),
+        )
+    }
+
+    pub fn task_stalled(task_name: &str, task_id: &str, idle_secs: u64) -> Self {
+        Self::new(
+            NotificationType::TaskStalled,
+            "任务可能已卡住".to_string(),
+            format!("任务 '{}' 已有 {} 秒没有新的输出或训练进度", task_name, idle_secs),
+            Some(task_id.to_string()),
+        )
+    }
+
+    pub fn sync_completed() -> Self {
+        Self::new(
+            NotificationType::SyncCompleted,
+            "代码同步完成".to_string(),
+            "代码同步操作已成功完成".to_string(),
+            None,
+        )
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationService {
+    sender: broadcast::Sender<Notification>,
+}
+
+impl Default for NotificationService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NotificationService {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(1000);
+        Self { sender }
+    }
+
+    pub fn send(&self, notification: Notification) {
+        let _ = self.sender.send(notification);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+}
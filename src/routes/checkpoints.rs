@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    path::{Path as FsPath, PathBuf},
+};
+
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, HeaderValue},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tower_http::services::ServeFile;
+use walkdir::WalkDir;
+
+use crate::{
+    error::AppError,
+    models::AppState,
+    routes::tasks::get_task_handler,
+    run_dir,
+};
+
+/// How far below the run directory checkpoints are searched for.
+const MAX_SEARCH_DEPTH: usize = 3;
+
+#[derive(Debug, Serialize)]
+pub struct CheckpointInfo {
+    /// Path relative to the run directory, used to download the checkpoint.
+    pub name: String,
+    /// Training iteration the checkpoint was saved at, from its file name.
+    pub iteration: Option<i64>,
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+    /// Metrics parsed from the log at the checkpoint's iteration.
+    pub metrics: HashMap<String, f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckpointsResponse {
+    pub run_dir: Option<PathBuf>,
+    pub checkpoints: Vec<CheckpointInfo>,
+}
+
+/// Lists the `model_*.pt` checkpoints in the task's run directory, oldest iteration first.
+pub async fn list_checkpoints_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<CheckpointsResponse>, AppError> {
+    let Json(task) = get_task_handler(State(state.clone()), Path(id.clone())).await?;
+    let Some(run_dir) = run_dir::task_run_dir(&state, &task).await? else {
+        return Ok(Json(CheckpointsResponse {
+            run_dir: None,
+            checkpoints: Vec::new(),
+        }));
+    };
+
+    let dir = run_dir.clone();
+    let mut checkpoints = tokio::task::spawn_blocking(move || find_checkpoints(&dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+
+    let iterations: Vec<i64> = checkpoints.iter().filter_map(|c| c.iteration).collect();
+    let mut metrics_by_iteration = metrics_at(&state.db, &id, &iterations).await?;
+    for checkpoint in &mut checkpoints {
+        if let Some(metrics) = checkpoint.iteration.and_then(|i| metrics_by_iteration.remove(&i)) {
+            checkpoint.metrics = metrics;
+        }
+    }
+
+    Ok(Json(CheckpointsResponse {
+        run_dir: Some(run_dir),
+        checkpoints,
+    }))
+}
+
+/// Serves a single checkpoint. Range requests are supported, so interrupted
+/// downloads of large checkpoints can be resumed.
+pub async fn download_checkpoint_handler(
+    State(state): State<AppState>,
+    Path((id, name)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    let Json(task) = get_task_handler(State(state.clone()), Path(id)).await?;
+    let not_found = || AppError::NotFound(format!("Checkpoint not found: {}", name));
+    let run_dir = run_dir::task_run_dir(&state, &task).await?.ok_or_else(not_found)?;
+
+    // Only files that are listed can be downloaded, which rules out any path outside
+    // the run directory.
+    let dir = run_dir.clone();
+    let checkpoints = tokio::task::spawn_blocking(move || find_checkpoints(&dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+    let checkpoint = checkpoints
+        .iter()
+        .find(|checkpoint| checkpoint.name == name)
+        .ok_or_else(not_found)?;
+    serve_attachment(&run_dir.join(&checkpoint.name), request).await
+}
+
+/// Serves a file with `ServeFile`, which answers range and conditional requests and
+/// sets the content type from the file extension.
+pub async fn serve_file(path: &FsPath, request: Request) -> Result<Response, AppError> {
+    Ok(ServeFile::new(path)
+        .try_call(request)
+        .await?
+        .map(Body::new)
+        .into_response())
+}
+
+/// Serves a file for download rather than for display.
+pub async fn serve_attachment(path: &FsPath, request: Request) -> Result<Response, AppError> {
+    let mut response = serve_file(path, request).await?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename=\"{}\"", file_name)) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, value);
+    }
+    Ok(response)
+}
+
+/// The metrics stored for a task at each of the given iterations.
+pub async fn metrics_at(
+    db: &SqlitePool,
+    task_id: &str,
+    iterations: &[i64],
+) -> Result<HashMap<i64, HashMap<String, f64>>, sqlx::Error> {
+    let rows = sqlx::query_as::<_, (i64, String, f64)>(
+        "SELECT iteration, key, value FROM metrics
+         WHERE task_id = ? AND iteration IN (SELECT value FROM json_each(?))",
+    )
+    .bind(task_id)
+    .bind(serde_json::json!(iterations).to_string())
+    .fetch_all(db)
+    .await?;
+    let mut metrics_by_iteration: HashMap<i64, HashMap<String, f64>> = HashMap::new();
+    for (iteration, key, value) in rows {
+        metrics_by_iteration.entry(iteration).or_default().insert(key, value);
+    }
+    Ok(metrics_by_iteration)
+}
+
+pub fn find_checkpoints(run_dir: &FsPath) -> Vec<CheckpointInfo> {
+    let name_regex = Regex::new(r"^model_(.*)\.pt$").unwrap();
+    let mut checkpoints: Vec<CheckpointInfo> = WalkDir::new(run_dir)
+        .max_depth(MAX_SEARCH_DEPTH)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let captures = name_regex.captures(entry.file_name().to_str()?)?;
+            let metadata = entry.metadata().ok()?;
+            Some(CheckpointInfo {
+                name: entry.path().strip_prefix(run_dir).ok()?.to_string_lossy().into_owned(),
+                iteration: captures[1].parse().ok(),
+                size: metadata.len(),
+                modified_at: metadata.modified().ok()?.into(),
+                metrics: HashMap::new(),
+            })
+        })
+        .collect();
+    checkpoints.sort_by(|a, b| {
+        (a.iteration.is_none(), a.iteration, &a.name).cmp(&(b.iteration.is_none(), b.iteration, &b.name))
+    });
+    checkpoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_checkpoints_in_iteration_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "model_1000.pt",
+            "model_50.pt",
+            "model_best.pt",
+            "nested/model_200.pt",
+            "model_300.pth",
+            "optimizer_300.pt",
+            "params/env.yaml",
+        ] {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"weights").unwrap();
+        }
+
+        let checkpoints = find_checkpoints(dir.path());
+        let found: Vec<(&str, Option<i64>)> = checkpoints
+            .iter()
+            .map(|checkpoint| (checkpoint.name.as_str(), checkpoint.iteration))
+            .collect();
+        // Checkpoints without an iteration in their name come last.
+        assert_eq!(
+            found,
+            vec![
+                ("model_50.pt", Some(50)),
+                ("nested/model_200.pt", Some(200)),
+                ("model_1000.pt", Some(1000)),
+                ("model_best.pt", None),
+            ]
+        );
+        assert!(checkpoints.iter().all(|checkpoint| checkpoint.size == 7));
+    }
+}
@@ -0,0 +1,34 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::stream::{self, Stream};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::models::AppState;
+
+/// Streams task and sync notifications to the client as server-sent events.
+pub async fn events_handler(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = state.notifications.subscribe();
+    let stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(notification) => {
+                    let event = Event::default()
+                        .event("notification")
+                        .json_data(&notification)
+                        .unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                // A slow client missed some events; keep streaming the newer ones.
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
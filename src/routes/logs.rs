@@ -0,0 +1,445 @@
+use std::{convert::Infallible, path::PathBuf, time::Duration};
+
+use async_compression::tokio::bufread::GzipEncoder;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+};
+use futures::{channel::mpsc, SinkExt, Stream, StreamExt};
+use notify::{RecursiveMode, Watcher};
+use regex::RegexBuilder;
+use serde::Deserialize;
+use tokio_util::io::ReaderStream;
+use tracing::warn;
+
+use crate::{
+    error::AppError,
+    log_reader,
+    models::{AppState, Task, TaskStatus},
+    terminal::{AnsiMode, RenderOptions},
+};
+
+const DEFAULT_PAGE_LINES: u64 = 200;
+const MAX_PAGE_LINES: u64 = 10_000;
+const DEFAULT_PAGE_BYTES: u64 = 64 * 1024;
+const MAX_PAGE_BYTES: u64 = 8 * 1024 * 1024;
+const DEFAULT_MAX_MATCHES: usize = 100;
+const MAX_GREP_MATCHES: usize = 10_000;
+const MAX_GREP_CONTEXT: usize = 50;
+
+/// Upper bound for a single `log` event while following a file.
+const MAX_EVENT_BYTES: usize = 256 * 1024;
+/// How often the task status is re-checked when the log is quiet.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogUnit {
+    #[default]
+    Lines,
+    Bytes,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogOrigin {
+    Head,
+    #[default]
+    Tail,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogQuery {
+    #[serde(default)]
+    pub unit: LogUnit,
+    /// Whether `offset` counts from the start or the end of the log.
+    #[serde(default)]
+    pub from: LogOrigin,
+    /// Lines or bytes to skip from `from` before the page starts.
+    pub offset: Option<u64>,
+    pub limit: Option<u64>,
+    /// Cursor returned in `X-Log-Cursor` (or `next_cursor` for grep) by the previous page.
+    pub cursor: Option<String>,
+    /// Regular expression; when set, matching lines are returned as JSON instead of a page.
+    pub grep: Option<String>,
+    #[serde(default)]
+    pub ignore_case: bool,
+    /// Lines of context returned around each match.
+    pub context: Option<usize>,
+    pub max_matches: Option<usize>,
+    /// Replay `\r` overwrites so progress bars show up as their final state.
+    #[serde(default)]
+    pub collapse_cr: bool,
+    /// `keep`, `strip` or `spans`; with `spans` pages are returned as JSON.
+    #[serde(default)]
+    pub ansi: AnsiMode,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LogStreamQuery {
+    /// Number of lines sent from the end of the log before following it, at most
+    /// `MAX_PAGE_LINES`.
+    pub tail: Option<usize>,
+    #[serde(default)]
+    pub collapse_cr: bool,
+    #[serde(default)]
+    pub ansi: AnsiMode,
+}
+
+/// Streams a task's log as server-sent events: the last `tail` lines first, then every
+/// line appended to the file. The stream waits for the log to appear if the task is still
+/// queued and ends with an `end` event carrying the final status and exit code.
+pub async fn stream_task_logs_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<LogStreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, AppError> {
+    fetch_task(&state, &id)
+        .await?
+        .ok_or_else(|| AppError::TaskNotFound(id.clone()))?;
+
+    let (sender, receiver) = mpsc::channel(16);
+    let render = RenderOptions {
+        collapse_cr: params.collapse_cr,
+        ansi: params.ansi,
+    };
+    let tail = params
+        .tail
+        .unwrap_or(DEFAULT_PAGE_LINES as usize)
+        .min(MAX_PAGE_LINES as usize);
+    tokio::spawn(follow_log(state, id, tail, render, sender));
+    Ok(Sse::new(receiver.map(Ok)).keep_alive(KeepAlive::default()))
+}
+
+/// Returns a page of a task's log as plain text. By default this is the last 200 lines;
+/// `unit`, `from`, `offset` and `limit` select other pages and `X-Log-Cursor` holds the
+/// cursor for the next one (older lines when reading from the tail, newer ones from the
+/// head). With `grep`, the log is searched instead and the matches are returned as JSON.
+/// The file is read in blocks, never as a whole.
+pub async fn get_task_logs_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<LogQuery>,
+) -> Result<Response, AppError> {
+    let task = fetch_task(&state, &id)
+        .await?
+        .ok_or_else(|| AppError::TaskNotFound(id.clone()))?;
+    let Some(log_path) = task.log_path.map(PathBuf::from) else {
+        return Ok("Log path not set.".into_response());
+    };
+    let log_size = match tokio::fs::metadata(&log_path).await {
+        Ok(metadata) => metadata.len(),
+        Err(_) if params.grep.is_some() => {
+            return Ok(Json(log_reader::GrepResult {
+                matches: Vec::new(),
+                next_cursor: None,
+            })
+            .into_response());
+        }
+        Err(_) => return Ok("Log not found or empty.".into_response()),
+    };
+
+    if let Some(pattern) = &params.grep {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(params.ignore_case)
+            .build()
+            .map_err(|e| AppError::BadRequest(format!("Invalid grep pattern: {}", e)))?;
+        let (start, first_line) = match &params.cursor {
+            Some(cursor) => log_reader::parse_grep_cursor(cursor)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid cursor: {}", cursor)))?,
+            None => (0, 1),
+        };
+        let context = params.context.unwrap_or(0).min(MAX_GREP_CONTEXT);
+        let max_matches = params.max_matches.unwrap_or(DEFAULT_MAX_MATCHES);
+        if max_matches == 0 {
+            return Err(AppError::BadRequest("max_matches must be at least 1".to_string()));
+        }
+        let max_matches = max_matches.min(MAX_GREP_MATCHES);
+        let render = RenderOptions {
+            collapse_cr: params.collapse_cr,
+            ansi: params.ansi,
+        };
+        let result = tokio::task::spawn_blocking(move || {
+            log_reader::grep(&log_path, &regex, context, max_matches, start, first_line, |line| {
+                render.render_text(line)
+            })
+        })
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))??;
+        return Ok(Json(result).into_response());
+    }
+
+    let cursor = params
+        .cursor
+        .as_deref()
+        .map(|c| {
+            c.parse::<u64>()
+                .map_err(|_| AppError::BadRequest(format!("Invalid cursor: {}", c)))
+        })
+        .transpose()?;
+    let offset = params.offset.unwrap_or(0);
+    // Every line takes at least a byte, so neither unit can skip more than the size.
+    if offset > log_size {
+        return Err(AppError::BadRequest(format!(
+            "Offset {} is past the end of the log ({} bytes)",
+            offset, log_size
+        )));
+    }
+    let (unit, from) = (params.unit, params.from);
+    let render = RenderOptions {
+        collapse_cr: params.collapse_cr,
+        ansi: params.ansi,
+    };
+    let chunk = tokio::task::spawn_blocking(move || match unit {
+        LogUnit::Lines => {
+            let limit = params
+                .limit
+                .unwrap_or(DEFAULT_PAGE_LINES)
+                .min(MAX_PAGE_LINES) as usize;
+            match (from, cursor) {
+                (LogOrigin::Head, Some(cursor)) => log_reader::lines_after(&log_path, cursor, 0, limit),
+                (LogOrigin::Head, None) => log_reader::lines_after(&log_path, 0, offset as usize, limit),
+                (LogOrigin::Tail, cursor) => {
+                    let skip = if cursor.is_some() { 0 } else { offset as usize };
+                    log_reader::lines_before(&log_path, cursor, skip, limit)
+                }
+            }
+        }
+        LogUnit::Bytes => {
+            let limit = params
+                .limit
+                .unwrap_or(DEFAULT_PAGE_BYTES)
+                .min(MAX_PAGE_BYTES);
+            let (start, end) = match from {
+                LogOrigin::Head => {
+                    let start = cursor.unwrap_or(offset);
+                    (start, start.saturating_add(limit))
+                }
+                LogOrigin::Tail => {
+                    let end = cursor.unwrap_or_else(|| log_size.saturating_sub(offset));
+                    (end.saturating_sub(limit), end)
+                }
+            };
+            log_reader::read_bytes(&log_path, start, end)
+        }
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e)))??;
+
+    let next_cursor = match from {
+        LogOrigin::Head => Some(chunk.end),
+        LogOrigin::Tail => Some(chunk.start).filter(|&start| start > 0),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("X-Log-Start", HeaderValue::from(chunk.start));
+    headers.insert("X-Log-End", HeaderValue::from(chunk.end));
+    headers.insert("X-Log-Size", HeaderValue::from(log_size));
+    if let Some(next_cursor) = next_cursor {
+        headers.insert("X-Log-Cursor", HeaderValue::from(next_cursor));
+    }
+    if render.ansi == AnsiMode::Spans {
+        let lines = render.render_json(chunk.lines);
+        return Ok((headers, Json(serde_json::json!({ "lines": lines }))).into_response());
+    }
+    let text = chunk
+        .lines
+        .into_iter()
+        .map(|line| render.render_text(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    Ok((headers, text).into_response())
+}
+
+/// Downloads the complete log of a task, gzip-compressed on the fly.
+pub async fn download_task_log_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let task = fetch_task(&state, &id)
+        .await?
+        .ok_or_else(|| AppError::TaskNotFound(id.clone()))?;
+    let log_path = task.log_path.ok_or_else(|| {
+        AppError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "Log path not set.",
+        ))
+    })?;
+
+    let file = tokio::fs::File::open(&log_path).await?;
+    let encoder = GzipEncoder::new(tokio::io::BufReader::new(file));
+    let body = Body::from_stream(ReaderStream::new(encoder));
+    let headers = [
+        (header::CONTENT_TYPE, "application/gzip".to_string()),
+        (
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{}.log.gz\"", id),
+        ),
+    ];
+    Ok((headers, body).into_response())
+}
+
+async fn fetch_task(state: &AppState, id: &str) -> Result<Option<Task>, sqlx::Error> {
+    sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await
+}
+
+async fn follow_log(
+    state: AppState,
+    id: String,
+    tail: usize,
+    render: RenderOptions,
+    mut sender: mpsc::Sender<Event>,
+) {
+    let (changed_tx, mut changed_rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = None;
+    // Byte offset just after the last line sent, and the file it refers to.
+    let mut position: Option<(u64, u64)> = None;
+
+    loop {
+        // The status is read before the log so that output written right before the
+        // process exited is always drained before the `end` event.
+        let task = match fetch_task(&state, &id).await {
+            Ok(Some(task)) => task,
+            Ok(None) => {
+                let _ = sender
+                    .send(Event::default().event("end").data("deleted"))
+                    .await;
+                return;
+            }
+            Err(e) => {
+                warn!("Log stream for task {} could not read the task: {}", id, e);
+                return;
+            }
+        };
+
+        let finished = !matches!(task.status, TaskStatus::Queued | TaskStatus::Running);
+        if let Some(log_path) = task.log_path.as_ref().map(PathBuf::from) {
+            if watcher.is_none() {
+                watcher = watch_log_dir(&log_path, changed_tx.clone());
+            }
+            while let Some((event, more)) =
+                read_new_lines(&log_path, tail, finished, render, &mut position).await
+            {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+                if !more {
+                    break;
+                }
+            }
+        }
+
+        if finished {
+            let end = Event::default().event("end").json_data(serde_json::json!({
+                "status": task.status,
+                "exit_code": task.exit_code,
+                "finished_at": task.finished_at,
+            }));
+            if let Ok(end) = end {
+                let _ = sender.send(end).await;
+            }
+            return;
+        }
+
+        if sender.is_closed() {
+            return;
+        }
+        tokio::select! {
+            _ = changed_rx.recv() => {}
+            _ = tokio::time::sleep(POLL_INTERVAL) => {}
+        }
+    }
+}
+
+/// Watches the directory containing the log, so that both appends and a re-created
+/// log file wake up the stream.
+fn watch_log_dir(
+    log_path: &std::path::Path,
+    changed: tokio::sync::mpsc::UnboundedSender<()>,
+) -> Option<notify::RecommendedWatcher> {
+    let dir = log_path.parent()?;
+    let mut watcher = notify::recommended_watcher(move |_| {
+        let _ = changed.send(());
+    })
+    .ok()?;
+    watcher.watch(dir, RecursiveMode::NonRecursive).ok()?;
+    Some(watcher)
+}
+
+/// Reads the next batch of complete lines. Returns the event to send and whether more
+/// data is already waiting, or `None` if there is nothing new. Once the task has
+/// `finished`, an unterminated last line is sent as well.
+async fn read_new_lines(
+    log_path: &std::path::Path,
+    tail: usize,
+    finished: bool,
+    render: RenderOptions,
+    position: &mut Option<(u64, u64)>,
+) -> Option<(Event, bool)> {
+    let metadata = tokio::fs::metadata(log_path).await.ok()?;
+    let identity = log_reader::file_identity(&metadata);
+    let len = metadata.len();
+
+    let (offset, rotated) = match *position {
+        Some((offset, known)) if known == identity && offset <= len => (offset, false),
+        Some(_) => (0, true),
+        None => {
+            let path = log_path.to_path_buf();
+            let chunk = tokio::task::spawn_blocking(move || log_reader::tail_lines(&path, tail))
+                .await
+                .ok()?
+                .ok()?;
+            *position = Some((chunk.end, identity));
+            let event = Event::default()
+                .event("log")
+                .json_data(serde_json::json!({
+                    "lines": render.render_json(chunk.lines),
+                    "start": chunk.start,
+                    "offset": chunk.end,
+                }))
+                .ok()?;
+            return Some((event, chunk.end < len));
+        }
+    };
+
+    let path = log_path.to_path_buf();
+    let bytes = tokio::task::spawn_blocking(move || {
+        log_reader::read_from(&path, offset, MAX_EVENT_BYTES)
+    })
+    .await
+    .ok()?
+    .ok()?;
+    // Only complete lines are sent; a partially written line is picked up next time.
+    let complete = match bytes.iter().rposition(|&b| b == b'\n') {
+        Some(i) => i + 1,
+        // A single line longer than the event limit is sent as is.
+        None if finished || bytes.len() == MAX_EVENT_BYTES => bytes.len(),
+        None => 0,
+    };
+    if complete == 0 && !rotated {
+        return None;
+    }
+    let end = offset + complete as u64;
+    *position = Some((end, identity));
+
+    let lines: Vec<String> = String::from_utf8_lossy(&bytes[..complete])
+        .lines()
+        .map(String::from)
+        .collect();
+    let event = Event::default()
+        .event("log")
+        .json_data(serde_json::json!({
+            "lines": render.render_json(lines),
+            "offset": end,
+            "rotated": rotated,
+        }))
+        .ok()?;
+    Some((event, end < len))
+}
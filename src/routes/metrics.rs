@@ -0,0 +1,492 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+};
+
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    error::AppError,
+    metric_groups::{self, MetricGroup},
+    metrics_parser::{MetricRules, MetricsData, MetricsParser},
+    models::{AppState, Task},
+    routes::tasks::get_task_handler,
+    series, tfevents,
+};
+
+/// Where a task's metrics are read from.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricsSource {
+    /// Metrics parsed from the task's log.
+    #[default]
+    Log,
+    /// Scalars from the TensorBoard event files the task wrote.
+    Tensorboard,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct MetricsQuery {
+    #[serde(default)]
+    pub source: MetricsSource,
+    /// First iteration to return, inclusive.
+    pub from_iteration: Option<i64>,
+    /// Last iteration to return, inclusive.
+    pub to_iteration: Option<i64>,
+    /// Comma-separated metric keys. By default all metrics except the fixed ones
+    /// (which are reported as `latest_fixed_metrics`) are returned.
+    pub keys: Option<String>,
+    /// Downsample every series to at most this many points.
+    pub max_points: Option<usize>,
+    #[serde(default)]
+    pub downsample: Downsample,
+    /// EMA smoothing weight in `[0, 1)`, applied before downsampling.
+    pub smoothing: Option<f64>,
+    /// Also return the min/max of the raw values in each of `max_points` buckets.
+    #[serde(default)]
+    pub envelope: bool,
+    /// Also return the metrics grouped by their `/`-separated namespaces.
+    #[serde(default)]
+    pub group: bool,
+}
+
+/// How long series are reduced to `max_points`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Downsample {
+    /// Largest-Triangle-Three-Buckets, which keeps the shape of the curve.
+    #[default]
+    Lttb,
+    /// The lowest and highest point of each bucket, which keeps every spike.
+    Minmax,
+}
+
+impl Downsample {
+    fn apply(self, points: &[(f64, f64)], max_points: usize) -> Vec<(f64, f64)> {
+        match self {
+            Downsample::Lttb => series::lttb(points, max_points),
+            Downsample::Minmax => series::minmax(points, max_points),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TaskMetricsResponse {
+    #[serde(flatten)]
+    pub data: MetricsData,
+    /// Raw value range per bucket, keyed like `historical_metrics`. Only present when
+    /// requested with `envelope` and `max_points`.
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub historical_envelopes: HashMap<String, Vec<series::EnvelopeBucket>>,
+    /// Namespace tree of `historical_metrics` with per-group summaries. Only present
+    /// when requested with `group`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub historical_groups: Option<MetricGroup>,
+}
+
+impl MetricsQuery {
+    fn keys(&self) -> Option<Vec<&str>> {
+        self.keys.as_deref().map(|keys| {
+            keys.split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .collect()
+        })
+    }
+
+    /// Smooths and downsamples the history in place, returning the envelopes of the
+    /// raw series if they were asked for.
+    fn reduce(
+        &self,
+        historical_metrics: &mut HashMap<String, Vec<(i64, f64)>>,
+    ) -> HashMap<String, Vec<series::EnvelopeBucket>> {
+        historical_metrics
+            .iter_mut()
+            .filter_map(|(key, points)| Some((key.clone(), self.reduce_series(points)?)))
+            .collect()
+    }
+
+    fn reduce_series(&self, points: &mut Vec<(i64, f64)>) -> Option<Vec<series::EnvelopeBucket>> {
+        let smoothing = self.smoothing.filter(|weight| *weight > 0.0);
+        if smoothing.is_none() && self.max_points.is_none() {
+            return None;
+        }
+        let mut series: Vec<(f64, f64)> = points.iter().map(|&(x, y)| (x as f64, y)).collect();
+        let envelope = self
+            .max_points
+            .filter(|_| self.envelope)
+            .map(|max_points| series::envelope(&series, max_points));
+        if let Some(weight) = smoothing {
+            series = series::ema(&series, weight);
+        }
+        if let Some(max_points) = self.max_points {
+            series = self.downsample.apply(&series, max_points);
+        }
+        // The x values are iterations; downsampling only picks existing points.
+        *points = series.into_iter().map(|(x, y)| (x as i64, y)).collect();
+        envelope
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct MetricRow {
+    iteration: i64,
+    key: String,
+    value: f64,
+}
+
+/// Rejects a smoothing weight outside `[0, 1)`, where EMA is not defined.
+fn check_smoothing(smoothing: Option<f64>) -> Result<(), AppError> {
+    match smoothing {
+        Some(weight) if !(0.0..1.0).contains(&weight) => Err(AppError::BadRequest(format!(
+            "smoothing must be in [0, 1), got {}",
+            weight
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Rejects a point limit below 2, as downsampling always keeps the first and last point.
+fn check_max_points(max_points: Option<usize>) -> Result<(), AppError> {
+    match max_points {
+        Some(max_points) if max_points < 2 => Err(AppError::BadRequest(format!(
+            "max_points must be at least 2, got {}",
+            max_points
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Returns a task's training metrics, smoothed and downsampled on request so that
+/// long runs stay cheap to chart.
+pub async fn get_task_metrics_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(params): Query<MetricsQuery>,
+) -> Result<Json<TaskMetricsResponse>, AppError> {
+    check_smoothing(params.smoothing)?;
+    check_max_points(params.max_points)?;
+    let task = get_task_handler(State(state.clone()), Path(id.clone()))
+        .await?
+        .0;
+    let mut data = match params.source {
+        MetricsSource::Log => log_metrics(&state, &task, &params).await?,
+        MetricsSource::Tensorboard => tensorboard_metrics(&state, &task, &params).await?,
+    };
+    // Summaries are computed from the raw series and then reduced like them.
+    let historical_groups = params.group.then(|| {
+        let mut groups = metric_groups::group_metrics(&data.historical_metrics);
+        groups.for_each_summary_series(&mut |points| {
+            params.reduce_series(points);
+        });
+        groups
+    });
+    let historical_envelopes = params.reduce(&mut data.historical_metrics);
+    Ok(Json(TaskMetricsResponse {
+        data,
+        historical_envelopes,
+        historical_groups,
+    }))
+}
+
+/// Reads what the ingester stored from the log: the history from the `metrics` table,
+/// the latest fixed metrics as printed from the task.
+async fn log_metrics(
+    state: &AppState,
+    task: &Task,
+    params: &MetricsQuery,
+) -> Result<MetricsData, AppError> {
+    let id = &task.id;
+
+    let keys = params.keys();
+    let keys_json = keys.as_ref().map(|keys| serde_json::json!(keys).to_string());
+    let rows = sqlx::query_as::<_, MetricRow>(
+        "SELECT iteration, key, value FROM metrics
+         WHERE task_id = ? AND (? IS NULL OR iteration >= ?) AND (? IS NULL OR iteration <= ?)
+           AND (? IS NULL OR key IN (SELECT value FROM json_each(?)))
+         ORDER BY iteration",
+    )
+    .bind(id)
+    .bind(params.from_iteration)
+    .bind(params.from_iteration)
+    .bind(params.to_iteration)
+    .bind(params.to_iteration)
+    .bind(&keys_json)
+    .bind(&keys_json)
+    .fetch_all(&state.db)
+    .await?;
+
+    let log_parser = task.log_format().parser();
+    let rules = task.metric_rules(&state.config.read().await.metrics);
+    let mut historical_metrics: HashMap<String, Vec<(i64, f64)>> = HashMap::new();
+    for row in rows {
+        // Rows stored before a key was excluded stay hidden as well.
+        if rules.is_excluded(&row.key)
+            || (keys.is_none() && rules.is_fixed(log_parser, &row.key))
+        {
+            continue;
+        }
+        historical_metrics
+            .entry(row.key)
+            .or_default()
+            .push((row.iteration, row.value));
+    }
+
+    let latest_fixed_metrics = match &task.latest_fixed_metrics {
+        Some(latest_fixed_metrics) => latest_fixed_metrics.0.clone(),
+        None => stored_fixed_metrics(state, id, log_parser, &rules).await?,
+    };
+
+    Ok(MetricsData {
+        latest_fixed_metrics,
+        historical_metrics,
+    })
+}
+
+/// The last stored value of each fixed metric, for tasks ingested before their printed
+/// values were kept.
+async fn stored_fixed_metrics(
+    state: &AppState,
+    task_id: &str,
+    log_parser: &dyn MetricsParser,
+    rules: &MetricRules,
+) -> Result<HashMap<String, String>, AppError> {
+    let rows = sqlx::query_as::<_, MetricRow>(
+        "SELECT m.iteration, m.key, m.value FROM metrics m
+         WHERE m.task_id = ? AND m.iteration = (
+             SELECT MAX(iteration) FROM metrics WHERE task_id = m.task_id AND key = m.key)",
+    )
+    .bind(task_id)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .filter(|row| rules.is_fixed(log_parser, &row.key) && !rules.is_excluded(&row.key))
+        .map(|row| (row.key, row.value.to_string()))
+        .collect())
+}
+
+/// Reads the scalars of the event files a task wrote to its run directory, or below
+/// its working directory while that is unknown. Tags map to keys and steps to
+/// iterations; there are no fixed metrics.
+async fn tensorboard_metrics(
+    state: &AppState,
+    task: &Task,
+    params: &MetricsQuery,
+) -> Result<MetricsData, AppError> {
+    let Some(started_at) = task.started_at else {
+        return Ok(MetricsData::default());
+    };
+    let search_root = match &task.run_dir {
+        Some(run_dir) => PathBuf::from(run_dir),
+        None => tfevents::search_root(&task.resolved_working_dir(&state.config.read().await.tasks)),
+    };
+    let finished_at = task.finished_at;
+
+    let scalars = tokio::task::spawn_blocking(move || {
+        let mut scalars = Vec::new();
+        for path in tfevents::find_event_files(&search_root, started_at, finished_at) {
+            match tfevents::read_scalars(&path) {
+                Ok(file_scalars) => scalars.extend(file_scalars),
+                Err(e) => warn!("Failed to read event file {}: {}", path.display(), e),
+            }
+        }
+        scalars
+    })
+    .await
+    .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+
+    let keys = params.keys();
+    let mut historical_metrics: HashMap<String, Vec<(i64, f64)>> = HashMap::new();
+    for scalar in scalars {
+        let in_range = params.from_iteration.is_none_or(|from| scalar.step >= from)
+            && params.to_iteration.is_none_or(|to| scalar.step <= to);
+        let wanted = keys
+            .as_ref()
+            .is_none_or(|keys| keys.contains(&scalar.tag.as_str()));
+        if in_range && wanted {
+            historical_metrics
+                .entry(scalar.tag)
+                .or_default()
+                .push((scalar.step, scalar.value));
+        }
+    }
+    for points in historical_metrics.values_mut() {
+        points.sort_by_key(|&(step, _)| step);
+    }
+
+    Ok(MetricsData {
+        latest_fixed_metrics: HashMap::new(),
+        historical_metrics,
+    })
+}
+
+/// How the series of different runs are lined up against each other.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Alignment {
+    #[default]
+    Iteration,
+    /// Seconds since the task started.
+    WallTime,
+    /// Environment steps taken, for runs with different numbers of environments.
+    Timesteps,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQuery {
+    /// Comma-separated task ids.
+    pub tasks: String,
+    /// Comma-separated metric keys.
+    pub keys: String,
+    #[serde(default)]
+    pub align: Alignment,
+    /// EMA smoothing weight in `[0, 1)`, as TensorBoard's smoothing slider.
+    pub smoothing: Option<f64>,
+    /// Downsample every series to at most this many points.
+    pub max_points: Option<usize>,
+    #[serde(default)]
+    pub downsample: Downsample,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareSeries {
+    pub task_id: String,
+    pub task_name: String,
+    pub key: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CompareResponse {
+    pub align: Alignment,
+    pub series: Vec<CompareSeries>,
+}
+
+#[derive(sqlx::FromRow)]
+struct TimedMetricRow {
+    iteration: i64,
+    key: String,
+    value: f64,
+    wall_time: DateTime<Utc>,
+}
+
+fn split_list(list: &str) -> Vec<&str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Returns the given metrics of several tasks on a common x axis, one series per task
+/// and key.
+pub async fn compare_metrics_handler(
+    State(state): State<AppState>,
+    Query(params): Query<CompareQuery>,
+) -> Result<Json<CompareResponse>, AppError> {
+    let task_ids = split_list(&params.tasks);
+    let keys = split_list(&params.keys);
+    if task_ids.is_empty() || keys.is_empty() {
+        return Err(AppError::BadRequest(
+            "Both tasks and keys must be given".to_string(),
+        ));
+    }
+    check_smoothing(params.smoothing)?;
+    check_max_points(params.max_points)?;
+
+    let mut compared = Vec::new();
+    for task_id in task_ids {
+        let task = get_task_handler(State(state.clone()), Path(task_id.to_string()))
+            .await?
+            .0;
+        let timesteps_key = task.log_format().parser().timesteps_key();
+        let mut queried_keys = keys.clone();
+        if params.align == Alignment::Timesteps {
+            queried_keys.extend(timesteps_key);
+        }
+
+        let rows = sqlx::query_as::<_, TimedMetricRow>(
+            "SELECT iteration, key, value, wall_time FROM metrics
+             WHERE task_id = ? AND key IN (SELECT value FROM json_each(?))
+             ORDER BY iteration",
+        )
+        .bind(&task.id)
+        .bind(serde_json::json!(queried_keys).to_string())
+        .fetch_all(&state.db)
+        .await?;
+
+        let origin = task
+            .started_at
+            .or_else(|| rows.iter().map(|row| row.wall_time).min());
+        let timesteps: HashMap<i64, f64> = rows
+            .iter()
+            .filter(|row| Some(row.key.as_str()) == timesteps_key)
+            .map(|row| (row.iteration, row.value))
+            .collect();
+        let x_of = |row: &TimedMetricRow| match params.align {
+            Alignment::Iteration => Some(row.iteration as f64),
+            Alignment::WallTime => origin
+                .map(|origin| (row.wall_time - origin).num_milliseconds() as f64 / 1000.0),
+            Alignment::Timesteps => timesteps.get(&row.iteration).copied(),
+        };
+
+        for key in &keys {
+            let mut points: Vec<(f64, f64)> = rows
+                .iter()
+                .filter(|row| row.key == *key)
+                .filter_map(|row| Some((x_of(row)?, row.value)))
+                .collect();
+            points.sort_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some(weight) = params.smoothing.filter(|weight| *weight > 0.0) {
+                points = series::ema(&points, weight);
+            }
+            if let Some(max_points) = params.max_points {
+                points = params.downsample.apply(&points, max_points);
+            }
+            compared.push(CompareSeries {
+                task_id: task.id.clone(),
+                task_name: task.name.clone(),
+                key: key.to_string(),
+                points,
+            });
+        }
+    }
+
+    Ok(Json(CompareResponse {
+        align: params.align,
+        series: compared,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoothing_must_be_below_one() {
+        for weight in [None, Some(0.0), Some(0.6), Some(0.999)] {
+            assert!(check_smoothing(weight).is_ok(), "{:?}", weight);
+        }
+        for weight in [-0.1, 1.0, 1.5, f64::NAN] {
+            let result = check_smoothing(Some(weight));
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{}", weight);
+        }
+    }
+
+    #[test]
+    fn max_points_must_keep_first_and_last() {
+        for max_points in [None, Some(2), Some(1000)] {
+            assert!(check_max_points(max_points).is_ok(), "{:?}", max_points);
+        }
+        for max_points in [0, 1] {
+            let result = check_max_points(Some(max_points));
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{}", max_points);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    routing::{any, get, post, put},
     Router,
 };
 use tower_http::{cors::CorsLayer, services::ServeDir};
@@ -7,29 +8,57 @@ use tower_http::{cors::CorsLayer, services::ServeDir};
 use crate::{
     models::AppState,
     routes::{
+        checkpoints::{download_checkpoint_handler, list_checkpoints_handler},
         config::{get_config_handler, update_config_handler},
+        events::events_handler,
         files::{delete_file_handler, list_files_handler},
+        logs::{download_task_log_handler, get_task_logs_handler, stream_task_logs_handler},
+        metrics::{compare_metrics_handler, get_task_metrics_handler},
+        prometheus::prometheus_metrics_handler,
+        registry::{
+            delete_model_handler, download_model_handler, get_model_handler, list_models_handler,
+            promote_model_handler,
+        },
+        search::search_logs_handler,
         static_files::index_handler,
         sync::{
             download_file_handler, download_zip_handler, get_sync_config_handler,
-            get_sync_manifest_handler, sync_code_handler,
+            get_sync_manifest_handler, sync_code_handler, sync_delete_handler,
+        },
+        sync_uploads::{
+            commit_chunked_upload_handler, get_chunked_upload_handler, put_chunk_handler,
+            start_chunked_upload_handler,
+        },
+        tensorboard::{
+            get_tensorboard_handler, start_tensorboard_handler, stop_tensorboard_handler,
+            tensorboard_proxy_handler, tensorboard_root_handler,
         },
         tasks::{
             create_task_handler, delete_task_handler, get_conda_envs_handler, get_queue_handler,
-            get_task_handler, get_task_logs_handler, get_task_metrics_handler, list_tasks_handler,
-            stop_task_handler,
+            get_task_handler, list_tasks_handler, stop_task_handler,
         },
+        videos::{list_videos_handler, stream_video_handler},
     },
 };
 
 use crate::routes::resources::get_resources_handler;
 
+pub mod checkpoints;
 pub mod config;
+pub mod events;
 pub mod files;
+pub mod logs;
+pub mod metrics;
+pub mod prometheus;
+pub mod registry;
 pub mod resources;
+pub mod search;
 pub mod static_files;
 pub mod sync;
+pub mod sync_uploads;
 pub mod tasks;
+pub mod tensorboard;
+pub mod videos;
 
 pub fn create_router(state: AppState) -> Router {
     Router::new()
@@ -44,16 +73,61 @@ pub fn create_router(state: AppState) -> Router {
         )
         .route("/api/tasks/{id}/stop", post(stop_task_handler))
         .route("/api/tasks/{id}/logs", get(get_task_logs_handler))
+        .route("/api/tasks/{id}/logs/stream", get(stream_task_logs_handler))
+        .route("/api/tasks/{id}/logs/download", get(download_task_log_handler))
         .route("/api/tasks/{id}/metrics", get(get_task_metrics_handler))
+        .route("/api/tasks/{id}/videos", get(list_videos_handler))
+        .route("/api/tasks/{id}/videos/{*name}", get(stream_video_handler))
+        .route("/api/tasks/{id}/checkpoints", get(list_checkpoints_handler))
+        .route(
+            "/api/tasks/{id}/checkpoints/{*name}",
+            get(download_checkpoint_handler),
+        )
+        .route(
+            "/api/tasks/{id}/tensorboard",
+            get(get_tensorboard_handler)
+                .post(start_tensorboard_handler)
+                .delete(stop_tensorboard_handler),
+        )
+        .route("/tensorboard/{id}", get(tensorboard_root_handler))
+        .route("/tensorboard/{id}/", any(tensorboard_proxy_handler))
+        .route("/tensorboard/{id}/{*path}", any(tensorboard_proxy_handler))
+        .route("/api/metrics/compare", get(compare_metrics_handler))
+        .route(
+            "/api/models",
+            get(list_models_handler).post(promote_model_handler),
+        )
+        .route(
+            "/api/models/{name}/{version}",
+            get(get_model_handler).delete(delete_model_handler),
+        )
+        .route(
+            "/api/models/{name}/{version}/download",
+            get(download_model_handler),
+        )
         .route("/api/conda/envs", get(get_conda_envs_handler))
         .route("/api/queue", get(get_queue_handler))
+        .route("/api/events", get(events_handler))
+        .route("/api/search/logs", get(search_logs_handler))
         .route(
             "/api/config",
             get(get_config_handler).post(update_config_handler),
         )
-        .route("/api/sync", post(sync_code_handler))
+        // Uploads are streamed to disk, so their size is not limited.
+        .route(
+            "/api/sync",
+            post(sync_code_handler).layer(DefaultBodyLimit::disable()),
+        )
         .route("/api/sync/config", get(get_sync_config_handler))
         .route("/api/sync/manifest", get(get_sync_manifest_handler))
+        .route("/api/sync/delete", post(sync_delete_handler))
+        .route("/api/sync/uploads", post(start_chunked_upload_handler))
+        .route("/api/sync/uploads/{id}", get(get_chunked_upload_handler))
+        .route("/api/sync/uploads/{id}/chunks/{index}", put(put_chunk_handler))
+        .route(
+            "/api/sync/uploads/{id}/commit",
+            post(commit_chunked_upload_handler),
+        )
         .route(
             "/api/files",
             get(list_files_handler).delete(delete_file_handler),
@@ -61,6 +135,7 @@ pub fn create_router(state: AppState) -> Router {
         .route("/api/sync/download/{*path}", get(download_file_handler))
         .route("/api/sync/download_zip", get(download_zip_handler))
         .route("/api/resources", get(get_resources_handler))
+        .route("/metrics", get(prometheus_metrics_handler))
         .nest_service("/static", ServeDir::new("static"))
         .layer(CorsLayer::permissive())
         .with_state(state)
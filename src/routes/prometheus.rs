@@ -0,0 +1,262 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::IntoResponse,
+};
+use chrono::{DateTime, Utc};
+
+use crate::{
+    error::AppError,
+    metrics::PrometheusText,
+    models::{AppState, Task, TaskStatus},
+    routes::resources::{get_cpu_info, get_gpu_info, get_memory_info, GpuInfo},
+};
+
+/// Upper bounds of the task duration histogram buckets, in seconds.
+const DURATION_BUCKETS: [f64; 9] = [
+    60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 14400.0, 28800.0, 86400.0,
+];
+
+#[derive(sqlx::FromRow)]
+struct FinishedTask {
+    status: TaskStatus,
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+}
+
+/// Exports the manager's counters, the task queue, the host's CPU/GPU load and the
+/// training progress of running tasks in the Prometheus text format.
+pub async fn prometheus_metrics_handler(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let mut text = PrometheusText::default();
+    write_manager_metrics(&state, &mut text).await?;
+    write_running_tasks(&state, &mut text).await?;
+    write_host_metrics(&mut text).await?;
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        text.finish(),
+    ))
+}
+
+async fn write_manager_metrics(state: &AppState, text: &mut PrometheusText) -> Result<(), AppError> {
+    let counters = state.metrics.snapshot();
+    text.family("isaaclab_manager_tasks_created_total", "counter", "Tasks created since the manager started.")
+        .sample("isaaclab_manager_tasks_created_total", &[], counters.tasks_created as f64)
+        .family("isaaclab_manager_tasks_completed_total", "counter", "Tasks that exited successfully since the manager started.")
+        .sample("isaaclab_manager_tasks_completed_total", &[], counters.tasks_completed as f64)
+        .family("isaaclab_manager_tasks_failed_total", "counter", "Tasks that failed since the manager started.")
+        .sample("isaaclab_manager_tasks_failed_total", &[], counters.tasks_failed as f64)
+        .family("isaaclab_manager_sync_operations_total", "counter", "Code syncs received since the manager started.")
+        .sample("isaaclab_manager_sync_operations_total", &[], counters.sync_operations as f64)
+        .family("isaaclab_manager_uptime_seconds", "gauge", "Seconds since the manager started.")
+        .sample("isaaclab_manager_uptime_seconds", &[], counters.uptime_seconds as f64);
+
+    let queue_depth = state.queue.lock().await.len();
+    text.family("isaaclab_manager_queue_depth", "gauge", "Tasks waiting in the queue.")
+        .sample("isaaclab_manager_queue_depth", &[], queue_depth as f64);
+
+    let by_status = sqlx::query_as::<_, (TaskStatus, i64)>(
+        "SELECT status, COUNT(*) FROM tasks GROUP BY status",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    text.family("isaaclab_manager_tasks", "gauge", "Tasks in the database by status.");
+    for (status, count) in by_status {
+        text.sample("isaaclab_manager_tasks", &[("status", status_label(status))], count as f64);
+    }
+
+    // Built from the database rather than observed live, so it survives restarts.
+    let finished = sqlx::query_as::<_, FinishedTask>(
+        "SELECT status, started_at, finished_at FROM tasks
+         WHERE started_at IS NOT NULL AND finished_at IS NOT NULL",
+    )
+    .fetch_all(&state.db)
+    .await?;
+    let name = "isaaclab_manager_task_duration_seconds";
+    text.family(name, "histogram", "Run time of finished tasks.");
+    for status in [TaskStatus::Completed, TaskStatus::Failed, TaskStatus::Stopped] {
+        let durations: Vec<f64> = finished
+            .iter()
+            .filter(|task| task.status == status)
+            .map(|task| (task.finished_at - task.started_at).num_milliseconds() as f64 / 1000.0)
+            .collect();
+        let status = status_label(status);
+        for bound in DURATION_BUCKETS {
+            let count = durations.iter().filter(|d| **d <= bound).count();
+            text.sample(
+                &format!("{}_bucket", name),
+                &[("status", status), ("le", &bound.to_string())],
+                count as f64,
+            );
+        }
+        text.sample(
+            &format!("{}_bucket", name),
+            &[("status", status), ("le", "+Inf")],
+            durations.len() as f64,
+        )
+        .sample(
+            &format!("{}_sum", name),
+            &[("status", status)],
+            durations.iter().fold(0.0, |sum, d| sum + d),
+        )
+        .sample(&format!("{}_count", name), &[("status", status)], durations.len() as f64);
+    }
+    Ok(())
+}
+
+/// Training progress of the running tasks, from the metrics ingested from their logs.
+async fn write_running_tasks(state: &AppState, text: &mut PrometheusText) -> Result<(), AppError> {
+    let running = sqlx::query_as::<_, Task>("SELECT * FROM tasks WHERE status = ?")
+        .bind(TaskStatus::Running)
+        .fetch_all(&state.db)
+        .await?;
+
+    let mut run_time = Vec::new();
+    let mut iterations = Vec::new();
+    let mut progress = Vec::new();
+    let mut rewards = Vec::new();
+    let mut fps = Vec::new();
+    for task in &running {
+        if let Some(started_at) = task.started_at {
+            let seconds = (Utc::now() - started_at).num_milliseconds() as f64 / 1000.0;
+            run_time.push((task, seconds));
+        }
+
+        if let Some(iteration) = task.current_iteration {
+            iterations.push((task, iteration as f64));
+        }
+        if let Some(progress_pct) = task.progress_pct {
+            progress.push((task, progress_pct));
+        }
+        if let Some(steps_per_sec) = task.steps_per_sec {
+            fps.push((task, steps_per_sec));
+        }
+
+        let Some(reward_key) = task.log_format().parser().reward_key() else {
+            continue;
+        };
+        let reward: Option<f64> = sqlx::query_scalar(
+            "SELECT value FROM metrics WHERE task_id = ? AND key = ? ORDER BY iteration DESC LIMIT 1",
+        )
+        .bind(&task.id)
+        .bind(reward_key)
+        .fetch_optional(&state.db)
+        .await?;
+        if let Some(reward) = reward {
+            rewards.push((task, reward));
+        }
+    }
+
+    for (name, help, samples) in [
+        ("isaaclab_manager_task_running_seconds", "Seconds since the running task started.", run_time),
+        ("isaaclab_manager_task_iteration", "Latest training iteration of the running task.", iterations),
+        ("isaaclab_manager_task_progress_percent", "Share of the planned iterations the running task has done.", progress),
+        ("isaaclab_manager_task_mean_reward", "Latest mean episode reward of the running task.", rewards),
+        ("isaaclab_manager_task_steps_per_second", "Latest training throughput of the running task.", fps),
+    ] {
+        text.family(name, "gauge", help);
+        for (task, value) in samples {
+            text.sample(name, &[("task_id", &task.id), ("task_name", &task.name)], value);
+        }
+    }
+    Ok(())
+}
+
+async fn write_host_metrics(text: &mut PrometheusText) -> Result<(), AppError> {
+    let (cpus, memory) = tokio::try_join!(get_cpu_info(), get_memory_info())?;
+    text.family("isaaclab_host_cpu_usage_percent", "gauge", "Usage of each CPU core.");
+    for (i, cpu) in cpus.iter().enumerate() {
+        text.sample("isaaclab_host_cpu_usage_percent", &[("cpu", &i.to_string())], cpu.usage as f64);
+    }
+    text.family("isaaclab_host_memory_total_bytes", "gauge", "Total memory of the host.")
+        .sample("isaaclab_host_memory_total_bytes", &[], memory.total as f64)
+        .family("isaaclab_host_memory_used_bytes", "gauge", "Memory in use on the host.")
+        .sample("isaaclab_host_memory_used_bytes", &[], memory.used as f64);
+
+    let gpus = get_gpu_info().await.unwrap_or_else(|e| {
+        tracing::warn!("Could not retrieve GPU info: {}", e);
+        Vec::new()
+    });
+    for (name, help, value_of) in [
+        ("isaaclab_host_gpu_utilization_percent", "GPU utilization.", (|gpu| gpu.utilization as f64) as fn(&GpuInfo) -> f64),
+        ("isaaclab_host_gpu_memory_used_bytes", "GPU memory in use.", |gpu| gpu.memory_used as f64),
+        ("isaaclab_host_gpu_memory_total_bytes", "Total GPU memory.", |gpu| gpu.memory_total as f64),
+        ("isaaclab_host_gpu_temperature_celsius", "GPU temperature.", |gpu| gpu.temperature as f64),
+        ("isaaclab_host_gpu_power_draw_watts", "GPU power draw.", |gpu| gpu.power_draw as f64),
+    ] {
+        text.family(name, "gauge", help);
+        for (i, gpu) in gpus.iter().enumerate() {
+            text.sample(name, &[("gpu", &i.to_string()), ("name", &gpu.name)], value_of(gpu));
+        }
+    }
+    Ok(())
+}
+
+fn status_label(status: TaskStatus) -> &'static str {
+    match status {
+        TaskStatus::Queued => "queued",
+        TaskStatus::Running => "running",
+        TaskStatus::Completed => "completed",
+        TaskStatus::Failed => "failed",
+        TaskStatus::Stopped => "stopped",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    async fn insert_task(state: &AppState, id: &str, status: TaskStatus, seconds: i64) {
+        let started_at = Utc::now() - chrono::Duration::days(1);
+        sqlx::query(
+            "INSERT INTO tasks (id, name, command, status, created_at, started_at, finished_at)
+             VALUES (?, 'train', 'python train.py', ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(status)
+        .bind(started_at)
+        .bind(started_at)
+        .bind(started_at + chrono::Duration::seconds(seconds))
+        .execute(&state.db)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn exports_task_durations_as_a_histogram() {
+        let state = AppState::for_tests(Config::default()).await;
+        insert_task(&state, "short", TaskStatus::Completed, 30).await;
+        insert_task(&state, "long", TaskStatus::Completed, 1000).await;
+        insert_task(&state, "endless", TaskStatus::Failed, 100_000).await;
+        let mut text = PrometheusText::default();
+        write_manager_metrics(&state, &mut text).await.unwrap();
+        let text = text.finish();
+        let lines: Vec<&str> = text.lines().collect();
+
+        let name = "isaaclab_manager_task_duration_seconds";
+        for expected in [
+            format!("# TYPE {} histogram", name),
+            format!("{}_bucket{{status=\"completed\",le=\"60\"}} 1", name),
+            format!("{}_bucket{{status=\"completed\",le=\"900\"}} 1", name),
+            format!("{}_bucket{{status=\"completed\",le=\"1800\"}} 2", name),
+            format!("{}_bucket{{status=\"completed\",le=\"+Inf\"}} 2", name),
+            format!("{}_sum{{status=\"completed\"}} 1030", name),
+            format!("{}_count{{status=\"completed\"}} 2", name),
+            format!("{}_bucket{{status=\"failed\",le=\"86400\"}} 0", name),
+            format!("{}_bucket{{status=\"failed\",le=\"+Inf\"}} 1", name),
+            format!("{}_count{{status=\"stopped\"}} 0", name),
+            "isaaclab_manager_tasks{status=\"completed\"} 2".to_string(),
+            "isaaclab_manager_tasks{status=\"failed\"} 1".to_string(),
+        ] {
+            assert!(lines.contains(&expected.as_str()), "missing {}", expected);
+        }
+        let buckets = lines
+            .iter()
+            .filter(|line| line.starts_with(&format!("{}_bucket{{status=\"completed\"", name)))
+            .count();
+        assert_eq!(buckets, DURATION_BUCKETS.len() + 1);
+    }
+}
@@ -0,0 +1,371 @@
+use std::path::{Path as FsPath, PathBuf};
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    response::Response,
+    Json,
+};
+use regex::Regex;
+use sha2::{Digest, Sha256};
+use tokio::fs as tokio_fs;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    error::AppError,
+    models::{AppState, ListModelsRequest, PromoteModelRequest, RegisteredModel},
+    routes::{
+        checkpoints::{find_checkpoints, metrics_at, serve_attachment},
+        tasks::get_task_handler,
+    },
+    run_dir,
+};
+
+// --- Model Registry Handlers ---
+
+/// Promotes a checkpoint of a task to a named model version. The checkpoint is
+/// hard-linked into the registry directory, or copied when that is on another file
+/// system, so the model outlives the task's outputs. It is staged in a directory of
+/// its own and only moved into place once the model is recorded, so a promotion that
+/// loses a race for the same version leaves the winner's files alone.
+pub async fn promote_model_handler(
+    State(state): State<AppState>,
+    Json(request): Json<PromoteModelRequest>,
+) -> Result<Json<RegisteredModel>, AppError> {
+    validate_segment("name", &request.name)?;
+    validate_segment("version", &request.version)?;
+    if fetch_model(&state, &request.name, &request.version).await?.is_some() {
+        return Err(AppError::BadRequest(format!(
+            "Model {}/{} already exists",
+            request.name, request.version
+        )));
+    }
+
+    let Json(task) = get_task_handler(State(state.clone()), Path(request.task_id.clone())).await?;
+    let not_found = || AppError::NotFound(format!("Checkpoint not found: {}", request.checkpoint));
+    let run_dir = run_dir::task_run_dir(&state, &task).await?.ok_or_else(not_found)?;
+    let dir = run_dir.clone();
+    let checkpoint = tokio::task::spawn_blocking(move || find_checkpoints(&dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?
+        .into_iter()
+        .find(|checkpoint| checkpoint.name == request.checkpoint)
+        .ok_or_else(not_found)?;
+
+    let registry_path = state.config.read().await.registry.path.clone();
+    let metrics = match checkpoint.iteration {
+        Some(iteration) => metrics_at(&state.db, &task.id, &[iteration])
+            .await?
+            .remove(&iteration)
+            .unwrap_or_default(),
+        None => Default::default(),
+    };
+
+    let model_dir = registry_path.join(&request.name).join(&request.version);
+    // Model names start with a letter or digit, so this never clashes with one.
+    let staging_dir = registry_path.join(".staging").join(Uuid::new_v4().to_string());
+    let source = run_dir.join(&checkpoint.name);
+    let file_name = source.file_name().unwrap_or_default().to_owned();
+    let file_path = model_dir.join(&file_name);
+    let staged_path = staging_dir.join(&file_name);
+    let staged = async {
+        tokio_fs::create_dir_all(&staging_dir).await?;
+        if tokio_fs::hard_link(&source, &staged_path).await.is_err() {
+            tokio_fs::copy(&source, &staged_path).await?;
+        }
+        tokio::task::spawn_blocking(move || -> std::io::Result<_> {
+            let mut hasher = Sha256::new();
+            std::io::copy(&mut std::fs::File::open(&staged_path)?, &mut hasher)?;
+            Ok(format!("{:x}", hasher.finalize()))
+        })
+        .await
+        .map_err(std::io::Error::other)?
+    }
+    .await;
+    let sha256 = match staged {
+        Ok(sha256) => sha256,
+        Err(e) => {
+            remove_staging_dir(&staging_dir).await;
+            return Err(e.into());
+        }
+    };
+
+    let model = RegisteredModel {
+        id: Uuid::new_v4().to_string(),
+        name: request.name.clone(),
+        version: request.version.clone(),
+        task_id: task.id.clone(),
+        checkpoint: checkpoint.name.clone(),
+        iteration: checkpoint.iteration,
+        command: task.command.clone(),
+        code_hash: task.code_hash.clone(),
+        notes: request.notes.clone().filter(|notes| !notes.trim().is_empty()),
+        metrics: sqlx::types::Json(metrics),
+        file_path: file_path.to_string_lossy().into_owned(),
+        size: checkpoint.size as i64,
+        sha256,
+        created_at: chrono::Utc::now(),
+    };
+
+    let inserted = sqlx::query(
+        "INSERT INTO models (id, name, version, task_id, checkpoint, iteration, command, code_hash, notes, metrics, file_path, size, sha256, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&model.id)
+    .bind(&model.name)
+    .bind(&model.version)
+    .bind(&model.task_id)
+    .bind(&model.checkpoint)
+    .bind(model.iteration)
+    .bind(&model.command)
+    .bind(&model.code_hash)
+    .bind(&model.notes)
+    .bind(&model.metrics)
+    .bind(&model.file_path)
+    .bind(model.size)
+    .bind(&model.sha256)
+    .bind(model.created_at)
+    .execute(&state.db)
+    .await;
+    if let Err(e) = inserted {
+        remove_staging_dir(&staging_dir).await;
+        if e.as_database_error().is_some_and(|e| e.is_unique_violation()) {
+            return Err(AppError::BadRequest(format!(
+                "Model {}/{} already exists",
+                request.name, request.version
+            )));
+        }
+        return Err(e.into());
+    }
+    let published = async {
+        tokio_fs::create_dir_all(registry_path.join(&request.name)).await?;
+        tokio_fs::rename(&staging_dir, &model_dir).await
+    }
+    .await;
+    if let Err(e) = published {
+        warn!("Failed to move model {}/{} into place: {}", model.name, model.version, e);
+        sqlx::query("DELETE FROM models WHERE id = ?")
+            .bind(&model.id)
+            .execute(&state.db)
+            .await?;
+        remove_staging_dir(&staging_dir).await;
+        return Err(e.into());
+    }
+
+    info!(
+        "Promoted checkpoint {} of task {} to model {}/{}",
+        model.checkpoint, model.task_id, model.name, model.version
+    );
+    Ok(Json(model))
+}
+
+pub async fn list_models_handler(
+    State(state): State<AppState>,
+    Query(params): Query<ListModelsRequest>,
+) -> Result<Json<Vec<RegisteredModel>>, AppError> {
+    let models = sqlx::query_as::<_, RegisteredModel>(
+        "SELECT * FROM models WHERE (? IS NULL OR name = ?) ORDER BY name, created_at DESC",
+    )
+    .bind(&params.name)
+    .bind(&params.name)
+    .fetch_all(&state.db)
+    .await?;
+    Ok(Json(models))
+}
+
+pub async fn get_model_handler(
+    State(state): State<AppState>,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<RegisteredModel>, AppError> {
+    fetch_model(&state, &name, &version)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Model not found: {}/{}", name, version)))
+}
+
+/// Serves the model's checkpoint, with range support like task checkpoints.
+pub async fn download_model_handler(
+    State(state): State<AppState>,
+    Path((name, version)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    let Json(model) = get_model_handler(State(state), Path((name, version))).await?;
+    serve_attachment(FsPath::new(&model.file_path), request).await
+}
+
+/// Removes a model version from the registry, including its file. The task and its
+/// checkpoint are left alone.
+pub async fn delete_model_handler(
+    State(state): State<AppState>,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let Json(model) = get_model_handler(State(state.clone()), Path((name, version))).await?;
+    sqlx::query("DELETE FROM models WHERE id = ?")
+        .bind(&model.id)
+        .execute(&state.db)
+        .await?;
+    if let Some(model_dir) = PathBuf::from(&model.file_path).parent() {
+        remove_model_dir(model_dir).await;
+    }
+    info!("Deleted model {}/{}", model.name, model.version);
+    Ok(Json(
+        serde_json::json!({ "message": format!("Model {}/{} deleted", model.name, model.version) }),
+    ))
+}
+
+async fn fetch_model(
+    state: &AppState,
+    name: &str,
+    version: &str,
+) -> Result<Option<RegisteredModel>, sqlx::Error> {
+    sqlx::query_as::<_, RegisteredModel>("SELECT * FROM models WHERE name = ? AND version = ?")
+        .bind(name)
+        .bind(version)
+        .fetch_optional(&state.db)
+        .await
+}
+
+/// Names and versions become directory names in the registry.
+fn validate_segment(field: &str, value: &str) -> Result<(), AppError> {
+    let valid = Regex::new(r"^[A-Za-z0-9][A-Za-z0-9._-]*$").unwrap();
+    if valid.is_match(value) {
+        Ok(())
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Model {} may only contain letters, digits, '.', '_' and '-': {}",
+            field, value
+        )))
+    }
+}
+
+async fn remove_staging_dir(staging_dir: &FsPath) {
+    if let Err(e) = tokio_fs::remove_dir_all(staging_dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove staging directory {}: {}", staging_dir.display(), e);
+        }
+    }
+}
+
+/// Removes a model's directory, and the model name's directory once it is empty.
+async fn remove_model_dir(model_dir: &FsPath) {
+    if let Err(e) = tokio_fs::remove_dir_all(model_dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove model directory {}: {}", model_dir.display(), e);
+        }
+    }
+    if let Some(name_dir) = model_dir.parent() {
+        // Fails while other versions are left, which is fine.
+        let _ = tokio_fs::remove_dir(name_dir).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, models::TaskStatus};
+
+    struct Fixture {
+        dir: tempfile::TempDir,
+        state: AppState,
+    }
+
+    impl Fixture {
+        /// A finished task with a `model_500.pt` checkpoint in its run directory.
+        async fn new() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let run_dir = dir.path().join("run");
+            std::fs::create_dir_all(&run_dir).unwrap();
+            std::fs::write(run_dir.join("model_500.pt"), b"weights").unwrap();
+            let mut config = Config::default();
+            config.registry.path = dir.path().join("registry");
+            let state = AppState::for_tests(config).await;
+            sqlx::query(
+                "INSERT INTO tasks (id, name, command, status, created_at, run_dir)
+                 VALUES ('task', 'train', 'python train.py', ?, ?, ?)",
+            )
+            .bind(TaskStatus::Completed)
+            .bind(chrono::Utc::now())
+            .bind(run_dir.to_string_lossy())
+            .execute(&state.db)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO metrics (task_id, iteration, key, value, wall_time)
+                 VALUES ('task', 500, 'Mean reward', 3.5, ?)",
+            )
+            .bind(chrono::Utc::now())
+            .execute(&state.db)
+            .await
+            .unwrap();
+            Self { dir, state }
+        }
+
+        fn registry(&self) -> PathBuf {
+            self.dir.path().join("registry")
+        }
+
+        async fn promote(&self, name: &str, version: &str) -> Result<RegisteredModel, AppError> {
+            let request = PromoteModelRequest {
+                task_id: "task".to_string(),
+                checkpoint: "model_500.pt".to_string(),
+                name: name.to_string(),
+                version: version.to_string(),
+                notes: None,
+            };
+            promote_model_handler(State(self.state.clone()), Json(request))
+                .await
+                .map(|Json(model)| model)
+        }
+
+        fn staged(&self) -> usize {
+            std::fs::read_dir(self.registry().join(".staging"))
+                .map(|entries| entries.count())
+                .unwrap_or(0)
+        }
+    }
+
+    #[test]
+    fn validates_names_and_versions() {
+        for value in ["anymal-d", "v1.0_final", "2026.03"] {
+            assert!(validate_segment("name", value).is_ok(), "{}", value);
+        }
+        for value in ["", ".staging", "..", "a/b", "../a", "-v1", "a b"] {
+            assert!(validate_segment("name", value).is_err(), "{}", value);
+        }
+    }
+
+    #[tokio::test]
+    async fn promotes_a_checkpoint_through_the_staging_dir() {
+        let fixture = Fixture::new().await;
+        let model = fixture.promote("anymal", "v1").await.unwrap();
+        let model_path = fixture.registry().join("anymal/v1/model_500.pt");
+        assert_eq!(PathBuf::from(&model.file_path), model_path);
+        assert_eq!(std::fs::read(&model_path).unwrap(), b"weights");
+        assert_eq!(model.sha256, format!("{:x}", Sha256::digest(b"weights")));
+        assert_eq!(model.iteration, Some(500));
+        assert_eq!(model.metrics.0["Mean reward"], 3.5);
+        assert_eq!(fixture.staged(), 0);
+
+        // A second promotion to the same version leaves the first one alone.
+        let again = fixture.promote("anymal", "v1").await;
+        assert!(matches!(again, Err(AppError::BadRequest(_))));
+        assert_eq!(std::fs::read(&model_path).unwrap(), b"weights");
+        assert_eq!(fixture.staged(), 0);
+
+        let path = Path(("anymal".to_string(), "v1".to_string()));
+        assert!(delete_model_handler(State(fixture.state.clone()), path).await.is_ok());
+        assert!(!fixture.registry().join("anymal").exists());
+    }
+
+    #[tokio::test]
+    async fn forgets_a_model_that_cannot_be_moved_into_place() {
+        let fixture = Fixture::new().await;
+        std::fs::create_dir_all(fixture.registry()).unwrap();
+        // A file where the model name's directory should go.
+        std::fs::write(fixture.registry().join("anymal"), b"").unwrap();
+
+        assert!(fixture.promote("anymal", "v1").await.is_err());
+        assert!(fetch_model(&fixture.state, "anymal", "v1").await.unwrap().is_none());
+        assert_eq!(fixture.staged(), 0);
+    }
+}
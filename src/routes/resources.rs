@@ -80,7 +80,7 @@ pub async fn get_resources_handler(
     }))
 }
 
-async fn get_cpu_info() -> Result<Vec<CpuInfo>, AppError> {
+pub async fn get_cpu_info() -> Result<Vec<CpuInfo>, AppError> {
     let cpuinfo_content = fs::read_to_string("/proc/cpuinfo").await?;
     let mut brand = "Unknown".to_string();
     let mut frequency: u64 = 0;
@@ -135,7 +135,7 @@ async fn get_cpu_info() -> Result<Vec<CpuInfo>, AppError> {
 
     // Fallback if we couldn't parse individual cores for some reason
     if cpus.is_empty() {
-        let num_cpus = std::thread::available_parallelism().map_err(|e| AppError::Io(e))?.get();
+        let num_cpus = std::thread::available_parallelism().map_err(AppError::Io)?.get();
         for _ in 0..num_cpus {
              cpus.push(CpuInfo {
                 brand: brand.clone(),
@@ -185,7 +185,7 @@ async fn read_proc_stat() -> Result<HashMap<String, ProcStat>, AppError> {
     Ok(stats)
 }
 
-async fn get_memory_info() -> Result<MemoryInfo, AppError> {
+pub async fn get_memory_info() -> Result<MemoryInfo, AppError> {
     let meminfo_content = fs::read_to_string("/proc/meminfo").await?;
     let mut total = 0;
     let mut available = 0;
@@ -211,7 +211,7 @@ async fn get_memory_info() -> Result<MemoryInfo, AppError> {
     })
 }
 
-async fn get_gpu_info() -> Result<Vec<GpuInfo>, anyhow::Error> {
+pub async fn get_gpu_info() -> Result<Vec<GpuInfo>, anyhow::Error> {
     let output = tokio::process::Command::new("nvidia-smi")
         .args([
             "--query-gpu=name,driver_version,memory.total,memory.used,utilization.gpu,temperature.gpu,power.draw,power.limit",
@@ -273,7 +273,7 @@ async fn get_top_cpu_processes() -> Result<Vec<CpuProcessInfo>, AppError> {
 
     for line in stdout.lines().skip(1).take(10) {
         // skip header and take top 10
-        let parts: Vec<&str> = line.trim().split_whitespace().collect();
+        let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() >= 3 {
             let usage: f32 = parts[0].parse().unwrap_or(0.0);
             let pid: u32 = parts[1].parse().unwrap_or(0);
@@ -322,7 +322,7 @@ async fn get_top_gpu_processes() -> Result<Vec<GpuProcessInfo>, AppError> {
     }
 
     // Sort by memory usage and take top 10
-    processes.sort_by(|a, b| b.memory_used.cmp(&a.memory_used));
+    processes.sort_by_key(|p| std::cmp::Reverse(p.memory_used));
     processes.truncate(10);
 
     Ok(processes)
@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::AppError,
+    models::{AppState, TaskStatus},
+};
+
+const DEFAULT_SEARCH_LIMIT: i64 = 100;
+const MAX_SEARCH_LIMIT: i64 = 1000;
+/// Control characters SQLite puts around the matched terms, which are swapped for
+/// `<mark>` tags once the rest of the line is HTML-escaped.
+const MATCH_START: char = '\u{2}';
+const MATCH_END: char = '\u{3}';
+
+#[derive(Debug, Deserialize)]
+pub struct LogSearchQuery {
+    /// An FTS5 query, e.g. `CUDA out of memory` or `"No module named" NOT torch`.
+    pub q: String,
+    pub task_id: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogSearchHit {
+    pub line_number: i64,
+    /// The matching line, HTML-escaped, with the matched terms wrapped in `<mark>` tags.
+    pub snippet: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LogSearchTaskResult {
+    pub task_id: String,
+    pub task_name: String,
+    pub status: TaskStatus,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub matches: Vec<LogSearchHit>,
+}
+
+#[derive(sqlx::FromRow)]
+struct SearchRow {
+    task_id: String,
+    task_name: String,
+    status: TaskStatus,
+    created_at: chrono::DateTime<chrono::Utc>,
+    line_number: i64,
+    snippet: String,
+}
+
+/// Searches the indexed logs of all tasks. Tasks are returned in order of their best
+/// match, each with its matching lines.
+pub async fn search_logs_handler(
+    State(state): State<AppState>,
+    Query(params): Query<LogSearchQuery>,
+) -> Result<Json<Vec<LogSearchTaskResult>>, AppError> {
+    if params.q.trim().is_empty() {
+        return Err(AppError::BadRequest("Search query must not be empty".to_string()));
+    }
+    let limit = params
+        .limit
+        .unwrap_or(DEFAULT_SEARCH_LIMIT)
+        .clamp(1, MAX_SEARCH_LIMIT);
+
+    let rows = sqlx::query_as::<_, SearchRow>(
+        "SELECT log_index.task_id, tasks.name AS task_name, tasks.status, tasks.created_at,
+                log_index.line_number, snippet(log_index, 0, char(2), char(3), '…', 24) AS snippet
+         FROM log_index JOIN tasks ON tasks.id = log_index.task_id
+         WHERE log_index MATCH ? AND (? IS NULL OR log_index.task_id = ?)
+         ORDER BY rank LIMIT ?",
+    )
+    .bind(&params.q)
+    .bind(&params.task_id)
+    .bind(&params.task_id)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await
+    .map_err(|e| match e {
+        // Syntax errors in the FTS5 query are reported by SQLite as database errors.
+        sqlx::Error::Database(db_error) => {
+            AppError::BadRequest(format!("Invalid search query: {}", db_error.message()))
+        }
+        e => AppError::Database(e),
+    })?;
+
+    let mut results: Vec<LogSearchTaskResult> = Vec::new();
+    for row in rows {
+        let hit = LogSearchHit {
+            line_number: row.line_number,
+            snippet: highlight(&row.snippet),
+        };
+        match results.iter_mut().find(|result| result.task_id == row.task_id) {
+            Some(result) => result.matches.push(hit),
+            None => results.push(LogSearchTaskResult {
+                task_id: row.task_id,
+                task_name: row.task_name,
+                status: row.status,
+                created_at: row.created_at,
+                matches: vec![hit],
+            }),
+        }
+    }
+    for result in &mut results {
+        result.matches.sort_by_key(|hit| hit.line_number);
+    }
+    Ok(Json(results))
+}
+
+/// HTML-escapes a snippet and turns its match markers into `<mark>` tags.
+fn highlight(snippet: &str) -> String {
+    let mut html = String::with_capacity(snippet.len());
+    for c in snippet.chars() {
+        match c {
+            MATCH_START => html.push_str("<mark>"),
+            MATCH_END => html.push_str("</mark>"),
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            '\'' => html.push_str("&#39;"),
+            c => html.push(c),
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_escapes_the_line_around_matches() {
+        assert_eq!(
+            highlight("<script>alert('x')</script> \u{2}CUDA\u{3} & \"oom\""),
+            "&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; <mark>CUDA</mark> &amp; &quot;oom&quot;"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use axum::{
     body::Body,
-    extract::{multipart::Multipart, Path, Query, State},
+    extract::{
+        multipart::{Field, Multipart},
+        Path, Query, State,
+    },
     http::header,
     response::{IntoResponse, Json},
 };
@@ -11,21 +14,23 @@ use std::{
     io::{Read, Write},
     path::PathBuf,
 };
-use tokio::fs as tokio_fs;
+use tokio::{fs as tokio_fs, io::AsyncWriteExt};
 use tokio_util::io::ReaderStream;
-use tracing::error;
+use tracing::{error, info, warn};
+use uuid::Uuid;
 use walkdir::WalkDir;
 use zip::write::{FileOptions, ZipWriter};
 
 use crate::{
     error::AppError,
-    models::{AppState, SyncConfigResponse, SyncRequest},
+    models::{AppState, SyncConfigResponse, SyncDeleteRequest, SyncRequest},
+    notifications::Notification,
 };
 
 // --- Sync Handlers ---
 
 /// A utility function to sanitize a path string, removing any directory traversal components.
-fn sanitize_path(path_str: &str) -> PathBuf {
+pub fn sanitize_path(path_str: &str) -> PathBuf {
     PathBuf::from(path_str)
         .components()
         .filter(|c| matches!(c, std::path::Component::Normal(_)))
@@ -35,7 +40,7 @@ fn sanitize_path(path_str: &str) -> PathBuf {
 /// Resolves the sync path based on configuration and an optional remote path from the client.
 /// If the remote path is provided, it must be absolute. Otherwise, the configured default is used.
 /// A security check ensures that the resolved path is within the application's CWD.
-async fn resolve_sync_path(
+pub async fn resolve_sync_path(
     config_path: &std::path::Path,
     remote_path_opt: Option<&String>,
 ) -> Result<PathBuf, AppError> {
@@ -102,51 +107,71 @@ pub async fn get_sync_manifest_handler(
     let target_path = resolve_sync_path(&base_path, params.remote_path.as_ref()).await?;
 
     let excludes = config.sync.default_excludes.clone();
-    let manifest = tokio::task::spawn_blocking(move || {
-        let exclude_patterns: Vec<glob::Pattern> = excludes
-            .iter()
-            .map(|s| glob::Pattern::new(s).expect("Invalid glob pattern in config"))
-            .collect();
-
-        let walker = WalkDir::new(&target_path).into_iter();
-        let filtered_walker = walker.filter_entry(|e| {
-            let path = e.path();
-            let relative_path = match path.strip_prefix(&target_path) {
-                Ok(p) => p,
-                Err(_) => return false,
-            };
-            if relative_path.as_os_str().is_empty() {
-                return true;
-            }
-            !exclude_patterns
-                .iter()
-                .any(|p| p.matches_path(relative_path))
-        });
-
-        let mut manifest: HashMap<String, String> = HashMap::new();
-        for result in filtered_walker {
-            if let Ok(entry) = result {
-                let path = entry.path();
-                if path.is_file() {
-                    if let Ok(relative_path) = path.strip_prefix(&target_path) {
-                        if let Ok(mut file) = File::open(path) {
-                            let mut hasher = Sha256::new();
-                            if std::io::copy(&mut file, &mut hasher).is_ok() {
-                                let hash = format!("{:x}", hasher.finalize());
-                                manifest
-                                    .insert(relative_path.to_string_lossy().replace('\\', "/"), hash);
-                            }
-                        }
+    let manifest = tokio::task::spawn_blocking(move || build_manifest(&target_path, &excludes))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+
+    Ok(Json(manifest))
+}
+
+/// Maps the path of every file below `target_path` that no exclude pattern matches to
+/// the SHA-256 of its content.
+pub fn build_manifest(target_path: &std::path::Path, excludes: &[String]) -> HashMap<String, String> {
+    let exclude_patterns: Vec<glob::Pattern> = excludes
+        .iter()
+        .map(|s| glob::Pattern::new(s).expect("Invalid glob pattern in config"))
+        .collect();
+
+    let walker = WalkDir::new(target_path).into_iter();
+    let filtered_walker = walker.filter_entry(|e| {
+        let path = e.path();
+        let relative_path = match path.strip_prefix(target_path) {
+            Ok(p) => p,
+            Err(_) => return false,
+        };
+        if relative_path.as_os_str().is_empty() {
+            return true;
+        }
+        !is_excluded(&exclude_patterns, relative_path, e.file_type().is_dir())
+    });
+
+    let mut manifest: HashMap<String, String> = HashMap::new();
+    for entry in filtered_walker.flatten() {
+        let path = entry.path();
+        if path.is_file() {
+            if let Ok(relative_path) = path.strip_prefix(target_path) {
+                if let Ok(mut file) = File::open(path) {
+                    let mut hasher = Sha256::new();
+                    if std::io::copy(&mut file, &mut hasher).is_ok() {
+                        let hash = format!("{:x}", hasher.finalize());
+                        manifest
+                            .insert(relative_path.to_string_lossy().replace('\\', "/"), hash);
                     }
                 }
             }
         }
-        manifest
-    })
-    .await
-    .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+    }
+    manifest
+}
 
-    Ok(Json(manifest))
+/// Whether an exclude pattern matches a path relative to the sync target. Patterns
+/// ending in `/`, like `logs/`, match directories.
+pub fn is_excluded(patterns: &[glob::Pattern], relative_path: &std::path::Path, is_dir: bool) -> bool {
+    let path = relative_path.to_string_lossy().replace('\\', "/");
+    patterns
+        .iter()
+        .any(|p| p.matches(&path) || (is_dir && p.matches(&format!("{}/", path))))
+}
+
+/// A single hash for a whole manifest, which changes whenever any file does.
+pub fn manifest_hash(manifest: &HashMap<String, String>) -> String {
+    let mut entries: Vec<_> = manifest.iter().collect();
+    entries.sort();
+    let mut hasher = Sha256::new();
+    for (path, hash) in entries {
+        hasher.update(format!("{}  {}\n", hash, path));
+    }
+    format!("{:x}", hasher.finalize())
 }
 
 pub async fn download_file_handler(
@@ -285,7 +310,7 @@ pub async fn download_zip_handler(
             // Find all .pt files and their modification times
             let mut pt_files = Vec::new();
             for entry in WalkDir::new(&target_path).into_iter().filter_map(|e| e.ok()) {
-                if entry.path().extension().map_or(false, |ext| ext == "pt") {
+                if entry.path().extension().is_some_and(|ext| ext == "pt") {
                     if let Ok(metadata) = entry.metadata() {
                         if let Ok(modified) = metadata.modified() {
                             pt_files.push((entry.path().to_path_buf(), modified));
@@ -296,7 +321,7 @@ pub async fn download_zip_handler(
 
             // Determine the newest .pt file
             let newest_pt_path = if !pt_files.is_empty() {
-                pt_files.sort_by(|a, b| b.1.cmp(&a.1)); // Sort descending by time
+                pt_files.sort_by_key(|f| std::cmp::Reverse(f.1)); // Sort descending by time
                 Some(pt_files[0].0.clone())
             } else {
                 None
@@ -320,7 +345,7 @@ pub async fn download_zip_handler(
                     }
 
                     // If it's a .pt file, only include the newest one
-                    if path.extension().map_or(false, |ext| ext == "pt") {
+                    if path.extension().is_some_and(|ext| ext == "pt") {
                         if let Some(newest) = &newest_pt_path {
                             if path != newest.as_path() {
                                 continue; // Skip this file
@@ -344,7 +369,7 @@ pub async fn download_zip_handler(
             Ok(buffer)
         })
         .await
-        .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
 
     let file_name = if let Some(remote_path) = &params.remote_path {
         let sanitized = sanitize_path(remote_path);
@@ -382,7 +407,7 @@ pub async fn sync_code_handler(
     tokio_fs::create_dir_all(&canonical_target).await?;
 
     let mut files_written = 0;
-    while let Some(field) = multipart.next_field().await? {
+    while let Some(mut field) = multipart.next_field().await? {
         if let Some(relative_path_str) = field.file_name() {
             let relative_path = sanitize_path(relative_path_str);
 
@@ -405,13 +430,287 @@ pub async fn sync_code_handler(
             if let Some(parent) = dest_path.parent() {
                 tokio_fs::create_dir_all(parent).await?;
             }
-            let data = field.bytes().await?;
-            tokio_fs::write(&dest_path, &data).await?;
+            write_field(&mut field, &dest_path).await?;
             files_written += 1;
         }
     }
 
+    state.metrics.increment_sync_operations();
+    state.notifications.send(Notification::sync_completed());
     Ok(Json(
         serde_json::json!({ "message": format!("Sync complete. Wrote {} files.", files_written) }),
     ))
 }
+
+/// Streams an uploaded file to a temporary file next to its destination and renames
+/// it into place once complete, so an interrupted upload never leaves a half-written
+/// file behind. Every part must carry the file's SHA-256 in an `x-sha256` header, which
+/// the content has to match.
+async fn write_field(field: &mut Field<'_>, dest_path: &std::path::Path) -> Result<(), AppError> {
+    let file_name = dest_path.file_name().unwrap_or_default().to_string_lossy();
+    let expected_hash = field
+        .headers()
+        .get("x-sha256")
+        .and_then(|value| value.to_str().ok())
+        .map(|hash| hash.trim().to_lowercase())
+        .ok_or_else(|| AppError::BadRequest(format!("Missing x-sha256 header for {}", file_name)))?;
+    // `*.tmp` files are left out of the manifest, should one survive a crash.
+    let temp_path = dest_path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+
+    let written = async {
+        let mut file = tokio_fs::File::create(&temp_path).await?;
+        let mut hasher = Sha256::new();
+        while let Some(chunk) = field.chunk().await? {
+            hasher.update(&chunk);
+            file.write_all(&chunk).await?;
+        }
+        file.flush().await?;
+        let hash = format!("{:x}", hasher.finalize());
+        if hash != expected_hash {
+            return Err(AppError::BadRequest(format!(
+                "Checksum mismatch for {}: expected {}, got {}",
+                file_name, expected_hash, hash
+            )));
+        }
+        // Make the content durable before the rename can replace the old file.
+        file.sync_all().await?;
+        tokio_fs::rename(&temp_path, dest_path).await?;
+        Ok(())
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = tokio_fs::remove_file(&temp_path).await;
+    }
+    written
+}
+
+/// Removes files that were deleted on the client from the sync target, so that an
+/// upload can mirror the local directory. Paths go through the same checks as uploads,
+/// excluded files are never touched, and directories left empty are removed as well.
+pub async fn sync_delete_handler(
+    State(state): State<AppState>,
+    Query(params): Query<SyncRequest>,
+    Json(request): Json<SyncDeleteRequest>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let config = state.config.read().await;
+    let base_path = PathBuf::from(&config.sync.target_path);
+    let canonical_target = resolve_sync_path(&base_path, params.remote_path.as_ref()).await?;
+    let exclude_patterns: Vec<glob::Pattern> = config
+        .sync
+        .default_excludes
+        .iter()
+        .filter_map(|s| glob::Pattern::new(s).ok())
+        .collect();
+
+    let mut files_deleted = 0;
+    for relative_path_str in &request.paths {
+        let relative_path = sanitize_path(relative_path_str);
+        if relative_path.as_os_str().is_empty() {
+            continue;
+        }
+        let excluded = relative_path
+            .ancestors()
+            .filter(|p| !p.as_os_str().is_empty())
+            .any(|p| is_excluded(&exclude_patterns, p, p != relative_path));
+        if excluded {
+            warn!("Refusing to delete excluded path: {}", relative_path.display());
+            continue;
+        }
+
+        // The parent is resolved rather than the file itself, so that a symlink is
+        // removed instead of the file it points to.
+        let dest_path = canonical_target.join(&relative_path);
+        let (Some(parent), Some(file_name)) = (dest_path.parent(), dest_path.file_name()) else {
+            continue;
+        };
+        let Ok(canonical_parent) = parent.canonicalize() else {
+            continue;
+        };
+        if !canonical_parent.starts_with(&canonical_target) {
+            error!(
+                "Security violation: file path '{}' escaped target directory '{}'",
+                dest_path.display(),
+                canonical_target.display()
+            );
+            continue;
+        }
+        let file_path = canonical_parent.join(file_name);
+        match tokio_fs::symlink_metadata(&file_path).await {
+            Ok(metadata) if !metadata.is_dir() => {}
+            _ => continue,
+        }
+        tokio_fs::remove_file(&file_path).await?;
+        files_deleted += 1;
+
+        // Fails once a directory still has entries, which ends the walk up.
+        for dir in canonical_parent.ancestors() {
+            if dir == canonical_target || !dir.starts_with(&canonical_target) {
+                break;
+            }
+            if tokio_fs::remove_dir(dir).await.is_err() {
+                break;
+            }
+        }
+    }
+
+    info!(
+        "Deleted {} files from sync target {}",
+        files_deleted,
+        canonical_target.display()
+    );
+    state.metrics.increment_sync_operations();
+    Ok(Json(serde_json::json!({
+        "message": format!("Deleted {} files.", files_deleted),
+        "deleted": files_deleted,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::FromRequest, http::Request};
+
+    use crate::config::Config;
+
+    /// A sync target inside the home directory, as `resolve_sync_path` requires, with
+    /// the directory around it to check that nothing escapes.
+    async fn sync_target() -> (tempfile::TempDir, PathBuf, AppState) {
+        let dir = tempfile::Builder::new()
+            .prefix(".sync-test")
+            .tempdir_in(home::home_dir().unwrap())
+            .unwrap();
+        let target = dir.path().join("target");
+        std::fs::create_dir(&target).unwrap();
+        let mut config = Config::default();
+        config.sync.target_path = target.canonicalize().unwrap();
+        config.sync.default_excludes = vec!["logs/".to_string(), "*.pyc".to_string()];
+        (dir, target, AppState::for_tests(config).await)
+    }
+
+    fn write(path: &std::path::Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    async fn upload(
+        state: &AppState,
+        file_name: &str,
+        content: &str,
+        sha256: Option<&str>,
+    ) -> Result<(), AppError> {
+        let boundary = "sync-test-boundary";
+        let sha256 = sha256.map(|hash| format!("x-sha256: {}\r\n", hash)).unwrap_or_default();
+        let body = format!(
+            "--{boundary}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{file_name}\"\r\n\
+             {sha256}\r\n\
+             {content}\r\n\
+             --{boundary}--\r\n"
+        );
+        let request = Request::builder()
+            .header(header::CONTENT_TYPE, format!("multipart/form-data; boundary={}", boundary))
+            .body(Body::from(body))
+            .unwrap();
+        let multipart = Multipart::from_request(request, state).await.unwrap();
+        let params = SyncRequest { remote_path: None };
+        sync_code_handler(State(state.clone()), Query(params), multipart)
+            .await
+            .map(|_| ())
+    }
+
+    async fn delete(state: &AppState, paths: &[&str]) -> usize {
+        let request = SyncDeleteRequest { paths: paths.iter().map(|p| p.to_string()).collect() };
+        let params = SyncRequest { remote_path: None };
+        let Json(response) = sync_delete_handler(State(state.clone()), Query(params), Json(request))
+            .await
+            .unwrap();
+        response["deleted"].as_u64().unwrap() as usize
+    }
+
+    fn sha256(content: &str) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    #[test]
+    fn sanitize_path_drops_traversal() {
+        assert_eq!(sanitize_path("../../etc/passwd"), PathBuf::from("etc/passwd"));
+        assert_eq!(sanitize_path("/root/.ssh/id_rsa"), PathBuf::from("root/.ssh/id_rsa"));
+        assert_eq!(sanitize_path("src/./a/../b.py"), PathBuf::from("src/a/b.py"));
+        assert_eq!(sanitize_path("../.."), PathBuf::new());
+    }
+
+    #[tokio::test]
+    async fn resolve_sync_path_stays_in_home() {
+        let (_dir, target, _) = sync_target().await;
+        let relative = "relative/dir".to_string();
+        assert!(resolve_sync_path(&target, Some(&relative)).await.is_err());
+        let outside = tempfile::tempdir_in("/tmp").unwrap();
+        let outside_path = outside.path().to_string_lossy().into_owned();
+        if !outside.path().starts_with(home::home_dir().unwrap()) {
+            assert!(resolve_sync_path(&target, Some(&outside_path)).await.is_err());
+        }
+        let resolved = resolve_sync_path(&target, None).await.unwrap();
+        assert_eq!(resolved, target.canonicalize().unwrap());
+    }
+
+    #[tokio::test]
+    async fn delete_removes_files_and_empty_directories() {
+        let (_dir, target, state) = sync_target().await;
+        write(&target.join("src/a.py"), "a");
+        write(&target.join("src/sub/deep/b.py"), "b");
+
+        assert_eq!(delete(&state, &["src/sub/deep/b.py", "src/missing.py"]).await, 1);
+        assert!(!target.join("src/sub").exists());
+        assert!(target.join("src/a.py").exists());
+
+        // Directories are never deleted directly, nor the target once it is empty.
+        assert_eq!(delete(&state, &["src"]).await, 0);
+        assert_eq!(delete(&state, &["src/a.py"]).await, 1);
+        assert!(!target.join("src").exists());
+        assert!(target.exists());
+    }
+
+    #[tokio::test]
+    async fn delete_refuses_excluded_and_escaping_paths() {
+        let (dir, target, state) = sync_target().await;
+        write(&target.join("logs/run/output.txt"), "log");
+        write(&target.join("cache.pyc"), "pyc");
+        write(&dir.path().join("outside.txt"), "outside");
+        write(&dir.path().join("linked/secret.txt"), "secret");
+        std::os::unix::fs::symlink(dir.path().join("linked"), target.join("link")).unwrap();
+
+        let paths = ["logs/run/output.txt", "cache.pyc", "../outside.txt", "link/secret.txt"];
+        assert_eq!(delete(&state, &paths).await, 0);
+        assert!(target.join("logs/run/output.txt").exists());
+        assert!(target.join("cache.pyc").exists());
+        assert!(dir.path().join("outside.txt").exists());
+        assert!(dir.path().join("linked/secret.txt").exists());
+
+        // A symlink itself is removed, not what it points to.
+        assert_eq!(delete(&state, &["link"]).await, 1);
+        assert!(target.join("link").symlink_metadata().is_err());
+        assert!(dir.path().join("linked/secret.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn upload_requires_a_matching_checksum() {
+        let (_dir, target, state) = sync_target().await;
+        write(&target.join("src/a.py"), "old");
+
+        let missing = upload(&state, "src/a.py", "new", None).await;
+        assert!(
+            matches!(missing, Err(AppError::BadRequest(message)) if message.contains("x-sha256"))
+        );
+        let wrong = upload(&state, "src/a.py", "new", Some(&sha256("other"))).await;
+        assert!(
+            matches!(wrong, Err(AppError::BadRequest(message)) if message.contains("Checksum"))
+        );
+        // The old file is untouched and no temporary file is left behind.
+        assert_eq!(std::fs::read_to_string(target.join("src/a.py")).unwrap(), "old");
+        assert_eq!(std::fs::read_dir(target.join("src")).unwrap().count(), 1);
+
+        upload(&state, "../src/a.py", "new", Some(&sha256("new"))).await.unwrap();
+        assert_eq!(std::fs::read_to_string(target.join("src/a.py")).unwrap(), "new");
+    }
+}
@@ -0,0 +1,531 @@
+use std::{
+    path::{Path as FsPath, PathBuf},
+    time::Duration,
+};
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::{
+    fs as tokio_fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::SyncConfig,
+    error::AppError,
+    models::{AppState, StartChunkedUploadRequest, SyncRequest},
+    notifications::Notification,
+    routes::sync::{resolve_sync_path, sanitize_path},
+};
+
+/// Size of every chunk but the last one.
+const CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+/// Uploads that have not received a chunk for this long are considered abandoned.
+const UPLOAD_EXPIRY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// --- Chunked Upload Handlers ---
+//
+// Large files are uploaded in chunks so an interrupted upload can pick up where it
+// stopped: the client starts an upload with the file's path, size and hash, `PUT`s
+// the chunks the server is missing and commits once all have arrived. Uploads are
+// identified by the file they carry, so starting the same upload again returns the
+// chunks received so far. Chunks are staged in a directory next to the sync target
+// until the commit assembles them at their destination. Abandoned uploads are removed
+// whenever a new upload starts.
+
+/// What is known about an upload, stored as `upload.json` in its staging directory.
+#[derive(Debug, Serialize, Deserialize)]
+struct ChunkedUpload {
+    /// Path relative to the target directory.
+    path: String,
+    target_dir: PathBuf,
+    size: u64,
+    sha256: String,
+}
+
+impl ChunkedUpload {
+    fn chunk_count(&self) -> u64 {
+        self.size.div_ceil(CHUNK_SIZE)
+    }
+
+    fn chunk_len(&self, index: u64) -> u64 {
+        (self.size - index * CHUNK_SIZE).min(CHUNK_SIZE)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChunkedUploadStatus {
+    pub upload_id: String,
+    pub path: String,
+    pub size: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+    /// Indices of the chunks the server has, in order.
+    pub received: Vec<u64>,
+}
+
+/// Starts an upload, or returns the progress of the same upload started before.
+pub async fn start_chunked_upload_handler(
+    State(state): State<AppState>,
+    Query(params): Query<SyncRequest>,
+    Json(request): Json<StartChunkedUploadRequest>,
+) -> Result<Json<ChunkedUploadStatus>, AppError> {
+    let config = state.config.read().await;
+    let canonical_target = resolve_sync_path(&config.sync.target_path, params.remote_path.as_ref()).await?;
+    let relative_path = sanitize_path(&request.path);
+    if relative_path.as_os_str().is_empty() {
+        return Err(AppError::BadRequest(format!("Invalid upload path: {}", request.path)));
+    }
+    if request.size > config.sync.max_upload_bytes {
+        return Err(AppError::BadRequest(format!(
+            "{} is {} bytes, uploads may be at most {} bytes",
+            request.path, request.size, config.sync.max_upload_bytes
+        )));
+    }
+    let sha256 = request.sha256.trim().to_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::BadRequest("sha256 must be a hex-encoded SHA-256".to_string()));
+    }
+
+    let upload = ChunkedUpload {
+        path: relative_path.to_string_lossy().replace('\\', "/"),
+        target_dir: canonical_target,
+        size: request.size,
+        sha256,
+    };
+    let upload_id = upload_id(&upload);
+    remove_expired_uploads(&staging_dir(&config.sync)).await;
+    let upload_dir = staging_dir(&config.sync).join(&upload_id);
+    if !tokio_fs::try_exists(upload_dir.join("upload.json")).await? {
+        tokio_fs::create_dir_all(&upload_dir).await?;
+        let json = serde_json::to_vec(&upload).map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+        tokio_fs::write(upload_dir.join("upload.json"), json).await?;
+        info!("Started chunked upload {} of {}", upload_id, upload.path);
+    }
+    Ok(Json(upload_status(&upload_id, &upload_dir, &upload).await?))
+}
+
+pub async fn get_chunked_upload_handler(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<ChunkedUploadStatus>, AppError> {
+    let upload_dir = upload_dir(&state, &upload_id).await?;
+    let upload = read_upload(&upload_dir, &upload_id).await?;
+    Ok(Json(upload_status(&upload_id, &upload_dir, &upload).await?))
+}
+
+/// Stores one chunk. A chunk sent again replaces the one received before. When the
+/// client sends the chunk's SHA-256 in an `x-sha256` header, the content must match it.
+pub async fn put_chunk_handler(
+    State(state): State<AppState>,
+    Path((upload_id, index)): Path<(String, u64)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let upload_dir = upload_dir(&state, &upload_id).await?;
+    let upload = read_upload(&upload_dir, &upload_id).await?;
+    if index >= upload.chunk_count() {
+        return Err(AppError::BadRequest(format!(
+            "Chunk {} is out of range, the upload has {} chunks",
+            index,
+            upload.chunk_count()
+        )));
+    }
+    let expected_len = upload.chunk_len(index);
+    let expected_hash = headers
+        .get("x-sha256")
+        .and_then(|value| value.to_str().ok())
+        .map(|hash| hash.trim().to_lowercase());
+
+    let temp_path = upload_dir.join(format!("{}.{}.tmp", index, Uuid::new_v4()));
+    let written = async {
+        let mut file = tokio_fs::File::create(&temp_path).await?;
+        let mut hasher = Sha256::new();
+        let mut len = 0;
+        let mut stream = body.into_data_stream();
+        while let Some(data) = stream
+            .try_next()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("Failed to read chunk: {}", e)))?
+        {
+            len += data.len() as u64;
+            if len > expected_len {
+                return Err(AppError::BadRequest(format!(
+                    "Chunk {} is longer than {} bytes",
+                    index, expected_len
+                )));
+            }
+            hasher.update(&data);
+            file.write_all(&data).await?;
+        }
+        file.flush().await?;
+        if len != expected_len {
+            return Err(AppError::BadRequest(format!(
+                "Chunk {} has {} bytes, expected {}",
+                index, len, expected_len
+            )));
+        }
+        let hash = format!("{:x}", hasher.finalize());
+        if expected_hash.as_ref().is_some_and(|expected_hash| hash != *expected_hash) {
+            return Err(AppError::BadRequest(format!("Checksum mismatch for chunk {}", index)));
+        }
+        tokio_fs::rename(&temp_path, chunk_path(&upload_dir, index)).await?;
+        Ok(())
+    }
+    .await;
+    if written.is_err() {
+        let _ = tokio_fs::remove_file(&temp_path).await;
+    }
+    written?;
+
+    Ok(Json(serde_json::json!({ "upload_id": upload_id, "chunk": index })))
+}
+
+/// Assembles the chunks at the file's destination once all of them have arrived.
+/// The file is only moved into place when its hash matches; otherwise the upload is
+/// dropped and has to start over.
+pub async fn commit_chunked_upload_handler(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let upload_dir = upload_dir(&state, &upload_id).await?;
+    let upload = read_upload(&upload_dir, &upload_id).await?;
+    let status = upload_status(&upload_id, &upload_dir, &upload).await?;
+    if status.received.len() as u64 != status.chunk_count {
+        return Err(AppError::BadRequest(format!(
+            "Upload {} has {} of {} chunks",
+            upload_id,
+            status.received.len(),
+            status.chunk_count
+        )));
+    }
+
+    let dest_path = upload.target_dir.join(&upload.path);
+    if let Some(parent) = dest_path.parent() {
+        tokio_fs::create_dir_all(parent).await?;
+    }
+    let file_name = dest_path.file_name().unwrap_or_default().to_string_lossy();
+    // `*.tmp` files are left out of the manifest, should one survive a crash.
+    let temp_path = dest_path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    let assembled = async {
+        let mut file = tokio_fs::File::create(&temp_path).await?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0; 64 * 1024];
+        for index in 0..upload.chunk_count() {
+            let mut chunk = tokio_fs::File::open(chunk_path(&upload_dir, index)).await?;
+            loop {
+                let n = chunk.read(&mut buffer).await?;
+                if n == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..n]);
+                file.write_all(&buffer[..n]).await?;
+            }
+        }
+        file.flush().await?;
+        // Make the content durable before the rename can replace the old file.
+        file.sync_all().await?;
+        Ok::<_, std::io::Error>(format!("{:x}", hasher.finalize()))
+    }
+    .await;
+    let hash = match assembled {
+        Ok(hash) => hash,
+        Err(e) => {
+            let _ = tokio_fs::remove_file(&temp_path).await;
+            return Err(e.into());
+        }
+    };
+    if hash != upload.sha256 {
+        let _ = tokio_fs::remove_file(&temp_path).await;
+        let _ = tokio_fs::remove_dir_all(&upload_dir).await;
+        return Err(AppError::BadRequest(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            upload.path, upload.sha256, hash
+        )));
+    }
+    tokio_fs::rename(&temp_path, &dest_path).await?;
+    tokio_fs::remove_dir_all(&upload_dir).await?;
+
+    info!("Completed chunked upload {} to {}", upload_id, dest_path.display());
+    state.metrics.increment_sync_operations();
+    state.notifications.send(Notification::sync_completed());
+    Ok(Json(serde_json::json!({ "message": format!("Uploaded {}.", upload.path) })))
+}
+
+/// Directory uploads are staged in: `.<name>.uploads` next to the sync target.
+fn staging_dir(config: &SyncConfig) -> PathBuf {
+    let name = config.target_path.file_name().unwrap_or_default().to_string_lossy();
+    config
+        .target_path
+        .with_file_name(format!(".{}.uploads", name))
+}
+
+/// Removes the staging directories of uploads that have not changed for `UPLOAD_EXPIRY`.
+/// A directory's modification time moves with every chunk stored in it.
+async fn remove_expired_uploads(staging_dir: &FsPath) {
+    let Ok(mut entries) = tokio_fs::read_dir(staging_dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let expired = entry.metadata().await.is_ok_and(|metadata| {
+            metadata.is_dir()
+                && metadata
+                    .modified()
+                    .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age >= UPLOAD_EXPIRY))
+        });
+        if !expired {
+            continue;
+        }
+        match tokio_fs::remove_dir_all(entry.path()).await {
+            Ok(()) => info!("Removed abandoned upload {}", entry.file_name().to_string_lossy()),
+            Err(e) => warn!("Failed to remove abandoned upload {}: {}", entry.path().display(), e),
+        }
+    }
+}
+
+/// The same file uploaded to the same place always gets the same ID, which is what
+/// lets an upload be resumed without the client keeping any state.
+fn upload_id(upload: &ChunkedUpload) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{}\n{}\n{}\n{}",
+        upload.target_dir.display(),
+        upload.path,
+        upload.size,
+        upload.sha256
+    ));
+    format!("{:x}", hasher.finalize())[..32].to_string()
+}
+
+/// The staging directory of an upload. IDs are checked before they become part of a path.
+async fn upload_dir(state: &AppState, upload_id: &str) -> Result<PathBuf, AppError> {
+    if upload_id.len() != 32 || !upload_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::NotFound(format!("Upload not found: {}", upload_id)));
+    }
+    Ok(staging_dir(&state.config.read().await.sync).join(upload_id))
+}
+
+async fn read_upload(upload_dir: &FsPath, upload_id: &str) -> Result<ChunkedUpload, AppError> {
+    let json = tokio_fs::read(upload_dir.join("upload.json"))
+        .await
+        .map_err(|_| AppError::NotFound(format!("Upload not found: {}", upload_id)))?;
+    serde_json::from_slice(&json).map_err(|e| AppError::Io(std::io::Error::other(e)))
+}
+
+fn chunk_path(upload_dir: &FsPath, index: u64) -> PathBuf {
+    upload_dir.join(format!("{}.chunk", index))
+}
+
+async fn upload_status(
+    upload_id: &str,
+    upload_dir: &FsPath,
+    upload: &ChunkedUpload,
+) -> Result<ChunkedUploadStatus, AppError> {
+    let mut received = Vec::new();
+    let mut entries = tokio_fs::read_dir(upload_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let name = entry.file_name();
+        let index = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".chunk"))
+            .and_then(|index| index.parse::<u64>().ok());
+        if let Some(index) = index.filter(|index| *index < upload.chunk_count()) {
+            received.push(index);
+        }
+    }
+    received.sort_unstable();
+    Ok(ChunkedUploadStatus {
+        upload_id: upload_id.to_string(),
+        path: upload.path.clone(),
+        size: upload.size,
+        chunk_size: CHUNK_SIZE,
+        chunk_count: upload.chunk_count(),
+        received,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    struct Fixture {
+        // Sync targets must be inside the home directory.
+        dir: tempfile::TempDir,
+        state: AppState,
+    }
+
+    impl Fixture {
+        async fn new() -> Self {
+            let home = home::home_dir().unwrap();
+            let dir = tempfile::Builder::new()
+                .prefix(".sync-uploads-test")
+                .tempdir_in(home)
+                .unwrap();
+            std::fs::create_dir(dir.path().join("target")).unwrap();
+            let mut config = Config::default();
+            config.sync.target_path = dir.path().join("target").canonicalize().unwrap();
+            Self { state: AppState::for_tests(config).await, dir }
+        }
+
+        fn target(&self) -> PathBuf {
+            self.dir.path().join("target")
+        }
+
+        fn staging(&self) -> PathBuf {
+            self.dir.path().join(".target.uploads")
+        }
+
+        async fn start(
+            &self,
+            path: &str,
+            size: u64,
+            sha256: &str,
+        ) -> Result<ChunkedUploadStatus, AppError> {
+            let request = StartChunkedUploadRequest {
+                path: path.to_string(),
+                size,
+                sha256: sha256.to_string(),
+            };
+            let params = SyncRequest { remote_path: None };
+            start_chunked_upload_handler(State(self.state.clone()), Query(params), Json(request))
+                .await
+                .map(|Json(status)| status)
+        }
+
+        async fn put(
+            &self,
+            id: &str,
+            index: u64,
+            data: &[u8],
+            sha256: Option<&str>,
+        ) -> Result<(), AppError> {
+            let mut headers = HeaderMap::new();
+            if let Some(sha256) = sha256 {
+                headers.insert("x-sha256", sha256.parse().unwrap());
+            }
+            let path = Path((id.to_string(), index));
+            put_chunk_handler(State(self.state.clone()), path, headers, Body::from(data.to_vec()))
+                .await
+                .map(|_| ())
+        }
+
+        async fn commit(&self, id: &str) -> Result<(), AppError> {
+            commit_chunked_upload_handler(State(self.state.clone()), Path(id.to_string()))
+                .await
+                .map(|_| ())
+        }
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    fn content(len: u64) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[tokio::test]
+    async fn resumes_and_commits_an_upload() {
+        let fixture = Fixture::new().await;
+        let data = content(CHUNK_SIZE + 100);
+        let (first, last) = data.split_at(CHUNK_SIZE as usize);
+
+        let size = data.len() as u64;
+        let status = fixture.start("dir/file.bin", size, &sha256(&data)).await.unwrap();
+        assert_eq!((status.chunk_count, status.received.len()), (2, 0));
+        fixture.put(&status.upload_id, 1, last, Some(&sha256(last))).await.unwrap();
+
+        // Starting the same file again resumes the upload.
+        let resumed = fixture.start("dir/file.bin", size, &sha256(&data)).await.unwrap();
+        assert_eq!(resumed.upload_id, status.upload_id);
+        assert_eq!(resumed.received, vec![1]);
+
+        let result = fixture.commit(&status.upload_id).await;
+        assert!(
+            matches!(result, Err(AppError::BadRequest(message)) if message.contains("1 of 2"))
+        );
+        assert!(!fixture.target().join("dir/file.bin").exists());
+
+        fixture.put(&status.upload_id, 0, first, None).await.unwrap();
+        fixture.commit(&status.upload_id).await.unwrap();
+        assert_eq!(std::fs::read(fixture.target().join("dir/file.bin")).unwrap(), data);
+        assert!(!fixture.staging().join(&status.upload_id).exists());
+    }
+
+    #[tokio::test]
+    async fn rejects_chunks_of_the_wrong_length_or_hash() {
+        let fixture = Fixture::new().await;
+        let data = content(100);
+        let status = fixture.start("file.bin", 100, &sha256(&data)).await.unwrap();
+        let id = &status.upload_id;
+
+        for chunk in [&data[..99], &content(101)[..]] {
+            let result = fixture.put(id, 0, chunk, None).await;
+            assert!(matches!(result, Err(AppError::BadRequest(_))), "{} bytes", chunk.len());
+        }
+        let out_of_range = fixture.put(id, 1, &data, None).await;
+        assert!(matches!(out_of_range, Err(AppError::BadRequest(_))));
+        let wrong_hash = fixture.put(id, 0, &data, Some(&sha256(b"other"))).await;
+        assert!(matches!(wrong_hash, Err(AppError::BadRequest(_))));
+
+        // Nothing was kept from the rejected chunks, not even their temporary files.
+        let files: Vec<_> = std::fs::read_dir(fixture.staging().join(id))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(files, vec!["upload.json"]);
+        let status = fixture.start("file.bin", 100, &sha256(&data)).await.unwrap();
+        assert!(status.received.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_uploads_above_the_size_limit() {
+        let fixture = Fixture::new().await;
+        fixture.state.config.write().await.sync.max_upload_bytes = 1000;
+        let result = fixture.start("file.bin", u64::MAX, &sha256(b"")).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+        assert!(fixture.start("file.bin", 1000, &sha256(b"")).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn drops_an_upload_whose_checksum_does_not_match() {
+        let fixture = Fixture::new().await;
+        let data = content(100);
+        let status = fixture.start("file.bin", 100, &sha256(b"something else")).await.unwrap();
+        fixture.put(&status.upload_id, 0, &data, None).await.unwrap();
+
+        let result = fixture.commit(&status.upload_id).await;
+        assert!(
+            matches!(result, Err(AppError::BadRequest(message)) if message.contains("Checksum"))
+        );
+        assert_eq!(std::fs::read_dir(fixture.target()).unwrap().count(), 0);
+        assert!(!fixture.staging().join(&status.upload_id).exists());
+    }
+
+    #[tokio::test]
+    async fn removes_expired_uploads() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["expired", "recent"] {
+            std::fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        let long_ago = std::time::SystemTime::now() - UPLOAD_EXPIRY - Duration::from_secs(60);
+        std::fs::File::open(dir.path().join("expired"))
+            .unwrap()
+            .set_modified(long_ago)
+            .unwrap();
+
+        remove_expired_uploads(dir.path()).await;
+        assert!(!dir.path().join("expired").exists());
+        assert!(dir.path().join("recent").exists());
+    }
+}
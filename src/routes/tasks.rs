@@ -2,18 +2,17 @@ use axum::{
     extract::{Path, State},
     Json,
 };
-use nix::{
-    sys::signal::{self, Signal},
-    unistd::Pid,
-};
 use tokio::process::Command;
-use tracing::{info, warn};
+use tracing::info;
 use uuid::Uuid;
 
 use crate::{
     error::AppError,
-    metrics_parser,
+    log_indexer, metrics_ingester,
+    metrics_parser::LogFormat,
     models::{AppState, CreateTaskRequest, Task, TaskStatus},
+    notifications::Notification,
+    task_manager,
 };
 
 // --- Route Handlers ---
@@ -29,18 +28,42 @@ pub async fn create_task_handler(
     State(state): State<AppState>,
     Json(request): Json<CreateTaskRequest>,
 ) -> Result<Json<Task>, AppError> {
-    let id = Uuid::new_v4().to_string();
+    for extractor in &request.metric_extractors {
+        if extractor.key.trim().is_empty() {
+            return Err(AppError::BadRequest("Metric extractors need a key".to_string()));
+        }
+        extractor.compile().map_err(AppError::BadRequest)?;
+    }
+    if request.eval_every.is_some_and(|every| every < 1) {
+        return Err(AppError::BadRequest("eval_every must be at least 1".to_string()));
+    }
+
+    let task = new_task(&state, request).await;
+    submit_task(&state, &task, QueuePriority::Normal).await?;
+    Ok(Json(task))
+}
+
+/// Where a new task joins the queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueuePriority {
+    /// Joins the queue like tasks created through the API.
+    Normal,
+    /// Starts only while no other task is queued or running.
+    Low,
+}
+
+/// Builds a queued task from a request, filling in the configured defaults.
+pub async fn new_task(state: &AppState, request: CreateTaskRequest) -> Task {
     let config = state.config.read().await;
     let conda_env = request
         .conda_env
         .unwrap_or_else(|| config.isaaclab.default_conda_env.clone());
-    let task_name = extract_task_name(&request.command);
 
-    let task = Task {
-        id: id.clone(),
-        name: task_name,
+    Task {
+        id: Uuid::new_v4().to_string(),
+        name: extract_task_name(&request.command),
         command: request.command.clone(),
-        conda_env: Some(conda_env.clone()),
+        conda_env: Some(conda_env),
         working_dir: request.working_dir.clone(),
         status: TaskStatus::Queued,
         pid: None,
@@ -48,25 +71,71 @@ pub async fn create_task_handler(
         started_at: None,
         finished_at: None,
         log_path: None,
-    };
-
-    sqlx::query("INSERT INTO tasks (id, name, command, conda_env, working_dir, status, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)")
-        .bind(&task.id)
-        .bind(&task.name)
-        .bind(&task.command)
-        .bind(&task.conda_env)
-        .bind(&task.working_dir)
-        .bind(&task.status)
-        .bind(task.created_at)
-        .execute(&state.db)
-        .await?;
+        tensorboard_port: None,
+        stalled_at: None,
+        retry_count: 0,
+        exit_code: None,
+        log_format: request
+            .log_format
+            .or_else(|| LogFormat::detect(&request.command)),
+        current_iteration: None,
+        total_iterations: None,
+        progress_pct: None,
+        steps_per_sec: None,
+        eta_seconds: None,
+        metric_extractors: (!request.metric_extractors.is_empty())
+            .then(|| sqlx::types::Json(request.metric_extractors.clone())),
+        failure_category: None,
+        failure_excerpt: None,
+        run_dir: None,
+        eval_every: request.eval_every,
+        eval_source_task_id: None,
+        eval_iteration: None,
+        code_hash: None,
+        latest_fixed_metrics: None,
+    }
+}
 
-    state.queue.lock().await.push(id.clone());
+/// Stores a new task and queues it.
+pub async fn submit_task(state: &AppState, task: &Task, priority: QueuePriority) -> Result<(), AppError> {
+    sqlx::query(
+        "INSERT INTO tasks (id, name, command, conda_env, working_dir, status, created_at, log_format, metric_extractors,
+         eval_every, eval_source_task_id, eval_iteration)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(&task.id)
+    .bind(&task.name)
+    .bind(&task.command)
+    .bind(&task.conda_env)
+    .bind(&task.working_dir)
+    .bind(task.status)
+    .bind(task.created_at)
+    .bind(task.log_format)
+    .bind(&task.metric_extractors)
+    .bind(task.eval_every)
+    .bind(&task.eval_source_task_id)
+    .bind(task.eval_iteration)
+    .execute(&state.db)
+    .await?;
+
+    // The task manager takes tasks from the end of the queue.
+    let mut queue = state.queue.lock().await;
+    match priority {
+        QueuePriority::Normal => queue.push(task.id.clone()),
+        QueuePriority::Low => queue.insert(0, task.id.clone()),
+    }
+    drop(queue);
+    state.metrics.increment_tasks_created();
+    state
+        .notifications
+        .send(Notification::task_created(&task.name, &task.id));
     info!(
         "Created task: {} with conda env: {} and command: {}",
-        id, conda_env, request.command
+        task.id,
+        task.conda_env.as_deref().unwrap_or_default(),
+        task.command
     );
-    Ok(Json(task))
+    Ok(())
 }
 
 pub async fn get_task_handler(
@@ -97,22 +166,7 @@ pub async fn stop_task_handler(
         .ok_or_else(|| AppError::TaskNotFound(id.clone()))?;
 
     if let Some(pid) = task.pid {
-        if pid > 0 {
-            info!("Attempting to stop process group with PID: {}", pid);
-            // Use nix to kill the entire process group by passing a negative PID.
-            let pgid = Pid::from_raw(-pid as i32);
-            match signal::kill(pgid, Signal::SIGKILL) {
-                Ok(_) => info!("Successfully sent SIGKILL to process group {}", pid),
-                Err(e) => {
-                    // It's not a critical error if the process doesn't exist (e.g., it already finished)
-                    // We can just log a warning.
-                    warn!(
-                        "Failed to kill process group {}: {}. This might be because the process already stopped.",
-                        pid, e
-                    );
-                }
-            }
-        }
+        task_manager::kill_process_group(pid);
     }
 
     let now = chrono::Utc::now();
@@ -139,64 +193,13 @@ pub async fn delete_task_handler(
         .bind(&id)
         .execute(&state.db)
         .await?;
+    log_indexer::remove_from_index(&state.db, &id).await?;
+    state.tensorboard.stop(&state.db, &id).await?;
+    metrics_ingester::remove_metrics(&state.db, &id).await?;
     state.tasks.write().await.remove(&id);
     Ok(Json(serde_json::json!({"message": "Task deleted"})))
 }
 
-pub async fn get_task_logs_handler(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<String, AppError> {
-    let task = get_task_handler(State(state), Path(id)).await?.0;
-    match task.log_path {
-        Some(log_path) => {
-            let content = tokio::fs::read_to_string(&log_path)
-                .await
-                .unwrap_or_else(|_| "Log not found or empty.".to_string());
-            let lines: Vec<&str> = content.lines().collect();
-            let last_200_lines = lines
-                .iter()
-                .rev()
-                .take(200)
-                .rev()
-                .map(|s| *s)
-                .collect::<Vec<&str>>()
-                .join("\n");
-            Ok(last_200_lines)
-        }
-        None => Ok("Log path not set.".to_string()),
-    }
-}
-
-pub async fn get_task_metrics_handler(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-) -> Result<Json<metrics_parser::MetricsData>, AppError> {
-    let task = get_task_handler(State(state.clone()), Path(id.clone()))
-        .await?
-        .0;
-    match task.log_path {
-        Some(log_path) => {
-            let content = tokio::fs::read_to_string(&log_path)
-                .await
-                .unwrap_or_else(|_| "".to_string());
-            let metrics = tokio::task::spawn_blocking(move || {
-                metrics_parser::parse_log_file(&content)
-            })
-            .await
-            .map_err(|e| AppError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
-            Ok(Json(metrics))
-        }
-        None => {
-            // Return empty metrics if log path is not set
-            Ok(Json(metrics_parser::MetricsData {
-                latest_fixed_metrics: std::collections::HashMap::new(),
-                historical_metrics: std::collections::HashMap::new(),
-            }))
-        }
-    }
-}
-
 pub async fn get_queue_handler(State(state): State<AppState>) -> Json<Vec<String>> {
     Json(state.queue.lock().await.clone())
 }
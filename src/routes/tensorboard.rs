@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Json,
+};
+use tracing::warn;
+
+use crate::{
+    error::AppError,
+    models::AppState,
+    routes::tasks::get_task_handler,
+    tensorboard::{self, TensorboardInfo},
+};
+
+/// Largest request body forwarded to TensorBoard. Its frontend only sends small
+/// queries, so anything bigger is rejected.
+const MAX_PROXY_BODY: usize = 1024 * 1024;
+
+/// Launches TensorBoard for a task, or returns the instance already running.
+pub async fn start_tensorboard_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<TensorboardInfo>, AppError> {
+    let task = get_task_handler(State(state.clone()), Path(id)).await?.0;
+    Ok(Json(state.tensorboard.start(&state, &task).await?))
+}
+
+pub async fn get_tensorboard_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Option<TensorboardInfo>>, AppError> {
+    Ok(Json(state.tensorboard.info(&id).await))
+}
+
+pub async fn stop_tensorboard_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    if state.tensorboard.stop(&state.db, &id).await? {
+        Ok(Json(serde_json::json!({"message": "TensorBoard stopped"})))
+    } else {
+        Ok(Json(serde_json::json!({"message": "TensorBoard is not running"})))
+    }
+}
+
+/// TensorBoard's pages use relative links, so its root needs the trailing slash.
+pub async fn tensorboard_root_handler(Path(id): Path<String>) -> Redirect {
+    Redirect::permanent(&format!("{}/", tensorboard::path_prefix(&id)))
+}
+
+/// Forwards a request under `/tensorboard/{id}/` to the task's TensorBoard. The
+/// instance runs with the same path prefix, so the path is passed through unchanged.
+pub async fn tensorboard_proxy_handler(
+    State(state): State<AppState>,
+    Path(params): Path<HashMap<String, String>>,
+    request: Request,
+) -> Response {
+    let Some(id) = params.get("id") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let Some(port) = state.tensorboard.touch(id).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            "TensorBoard is not running for this task. Start it from the task page first.",
+        )
+            .into_response();
+    };
+
+    let path_and_query = request
+        .uri()
+        .path_and_query()
+        .map(|pq| pq.as_str())
+        .unwrap_or("/");
+    let url = format!("http://127.0.0.1:{}{}", port, path_and_query);
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, MAX_PROXY_BODY).await {
+        Ok(body) => body,
+        Err(_) => return StatusCode::PAYLOAD_TOO_LARGE.into_response(),
+    };
+
+    let upstream = state
+        .tensorboard
+        .client()
+        .request(parts.method, url)
+        .headers(forwarded_headers(&parts.headers))
+        .body(body)
+        .send()
+        .await;
+    let upstream = match upstream {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            warn!("TensorBoard proxy request for task {} failed: {}", id, e);
+            return (StatusCode::BAD_GATEWAY, "TensorBoard is not responding").into_response();
+        }
+    };
+
+    let mut response = Response::builder().status(upstream.status());
+    if let Some(headers) = response.headers_mut() {
+        *headers = forwarded_headers(upstream.headers());
+    }
+    response
+        .body(Body::from_stream(upstream.bytes_stream()))
+        .unwrap_or_else(|_| StatusCode::BAD_GATEWAY.into_response())
+}
+
+/// Copies the end-to-end headers of a proxied request or response.
+fn forwarded_headers(headers: &HeaderMap) -> HeaderMap {
+    const HOP_BY_HOP: [HeaderName; 8] = [
+        header::CONNECTION,
+        header::HOST,
+        header::PROXY_AUTHENTICATE,
+        header::PROXY_AUTHORIZATION,
+        header::TE,
+        header::TRAILER,
+        header::TRANSFER_ENCODING,
+        header::UPGRADE,
+    ];
+    headers
+        .iter()
+        .filter(|(name, _)| !HOP_BY_HOP.contains(name) && name.as_str() != "keep-alive")
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect()
+}
@@ -0,0 +1,174 @@
+use std::path::{Path as FsPath, PathBuf};
+
+use axum::{
+    extract::{Path, Request, State},
+    response::Response,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::{
+    error::AppError,
+    models::AppState,
+    routes::{checkpoints::serve_file, tasks::get_task_handler},
+    run_dir,
+};
+
+/// Video formats browsers can play.
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm"];
+
+/// How far below the videos directory recordings are searched for.
+const MAX_SEARCH_DEPTH: usize = 3;
+
+// IsaacLab records with gymnasium's `RecordVideo` to `<run dir>/videos/train` while
+// training and `<run dir>/videos/play` when playing a checkpoint, naming the files
+// after the trigger: `rl-video-step-2000.mp4` or `rl-video-episode-3.mp4`.
+
+#[derive(Debug, Serialize)]
+pub struct VideoInfo {
+    /// Path relative to the run's `videos` directory, used to stream the video.
+    pub name: String,
+    /// What the video was recorded during, e.g. `train` or `play`.
+    pub kind: Option<String>,
+    /// Environment step at which recording started, for step-triggered videos.
+    pub step: Option<i64>,
+    /// Episode that was recorded, for episode-triggered videos.
+    pub episode: Option<i64>,
+    pub size: u64,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideosResponse {
+    pub videos_dir: Option<PathBuf>,
+    pub videos: Vec<VideoInfo>,
+}
+
+/// Lists the videos recorded in the task's run directory, in recording order.
+pub async fn list_videos_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<VideosResponse>, AppError> {
+    let Json(task) = get_task_handler(State(state.clone()), Path(id)).await?;
+    let Some(videos_dir) = run_dir::task_run_dir(&state, &task).await?.map(|dir| dir.join("videos")) else {
+        return Ok(Json(VideosResponse {
+            videos_dir: None,
+            videos: Vec::new(),
+        }));
+    };
+    let dir = videos_dir.clone();
+    let videos = tokio::task::spawn_blocking(move || find_videos(&dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+    Ok(Json(VideosResponse {
+        videos_dir: Some(videos_dir),
+        videos,
+    }))
+}
+
+/// Streams a video. Range requests are answered so browsers can seek.
+pub async fn stream_video_handler(
+    State(state): State<AppState>,
+    Path((id, name)): Path<(String, String)>,
+    request: Request,
+) -> Result<Response, AppError> {
+    let Json(task) = get_task_handler(State(state.clone()), Path(id)).await?;
+    let not_found = || AppError::NotFound(format!("Video not found: {}", name));
+    let videos_dir = run_dir::task_run_dir(&state, &task)
+        .await?
+        .ok_or_else(not_found)?
+        .join("videos");
+
+    // Only listed videos are served, which rules out any path outside the directory.
+    let dir = videos_dir.clone();
+    let videos = tokio::task::spawn_blocking(move || find_videos(&dir))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+    let video = videos.iter().find(|video| video.name == name).ok_or_else(not_found)?;
+    serve_file(&videos_dir.join(&video.name), request).await
+}
+
+fn find_videos(videos_dir: &FsPath) -> Vec<VideoInfo> {
+    let trigger_regex = Regex::new(r"-(step|episode)-(\d+)\.\w+$").unwrap();
+    let mut videos: Vec<VideoInfo> = WalkDir::new(videos_dir)
+        .max_depth(MAX_SEARCH_DEPTH)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| {
+            entry
+                .path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .filter_map(|entry| {
+            let relative = entry.path().strip_prefix(videos_dir).ok()?;
+            let metadata = entry.metadata().ok()?;
+            let file_name = entry.file_name().to_string_lossy();
+            let trigger = trigger_regex.captures(&file_name);
+            let number = |kind: &str| {
+                trigger
+                    .as_ref()
+                    .filter(|captures| &captures[1] == kind)
+                    .and_then(|captures| captures[2].parse().ok())
+            };
+            Some(VideoInfo {
+                name: relative.to_string_lossy().into_owned(),
+                kind: relative
+                    .parent()
+                    .and_then(|parent| parent.components().next())
+                    .map(|kind| kind.as_os_str().to_string_lossy().into_owned()),
+                step: number("step"),
+                episode: number("episode"),
+                size: metadata.len(),
+                modified_at: metadata.modified().ok()?.into(),
+            })
+        })
+        .collect();
+    videos.sort_by(|a, b| {
+        (&a.kind, a.step, a.episode, a.modified_at, &a.name).cmp(&(&b.kind, b.step, b.episode, b.modified_at, &b.name))
+    });
+    videos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_video_names() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in [
+            "train/rl-video-step-2000.mp4",
+            "train/rl-video-step-0.mp4",
+            "play/rl-video-episode-3.webm",
+            "play/recording.MP4",
+            "train/rl-video-step-4000.meta.json",
+            "rl-video-step-10.mp4",
+        ] {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, b"video").unwrap();
+        }
+
+        let videos = find_videos(dir.path());
+        let found: Vec<_> = videos
+            .iter()
+            .map(|video| (video.name.as_str(), video.kind.as_deref(), video.step, video.episode))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("rl-video-step-10.mp4", None, Some(10), None),
+                ("play/recording.MP4", Some("play"), None, None),
+                ("play/rl-video-episode-3.webm", Some("play"), None, Some(3)),
+                ("train/rl-video-step-0.mp4", Some("train"), Some(0), None),
+                ("train/rl-video-step-2000.mp4", Some("train"), Some(2000), None),
+            ]
+        );
+    }
+}
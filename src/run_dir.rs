@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+
+use crate::{
+    error::AppError,
+    log_reader,
+    models::{AppState, Task},
+    tfevents,
+};
+
+// --- Run directory detection ---
+//
+// IsaacLab's training scripts print where a run logs to before training starts:
+//
+//     [INFO] Logging experiment in directory: /workspace/isaaclab/logs/rsl_rl/anymal_d_flat
+//     Exact experiment name requested from command line: 2026-03-02_10-15-42
+//
+// and then write to `<directory>/<name>`, with a `_<run name>` suffix appended to the
+// name after it was printed when the run is named. Play scripts write their videos to
+// the run directory of the checkpoint they load:
+//
+//     [INFO]: Loading model checkpoint from: /workspace/isaaclab/logs/rsl_rl/anymal_d_flat/2026-03-02_10-15-42/model_1499.pt
+
+/// How much of the start of the log is searched for the run directory.
+const LOG_SCAN_BYTES: usize = 4 * 1024 * 1024;
+
+/// Finds the run directory of a task, from the lines its training script printed or,
+/// failing that, from the event files it wrote below its working directory. Returns
+/// `None` while the directory does not exist yet.
+pub fn detect(
+    log_path: &Path,
+    working_dir: &Path,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: Option<DateTime<Utc>>,
+) -> Option<PathBuf> {
+    let head = log_reader::read_from(log_path, 0, LOG_SCAN_BYTES).unwrap_or_default();
+    from_log(&String::from_utf8_lossy(&head), working_dir)
+        .or_else(|| tfevents::find_run_dir(working_dir, started_at?, finished_at))
+}
+
+fn from_log(log: &str, working_dir: &Path) -> Option<PathBuf> {
+    training_run_dir(log, working_dir).or_else(|| loaded_run_dir(log, working_dir))
+}
+
+fn training_run_dir(log: &str, working_dir: &Path) -> Option<PathBuf> {
+    let directory_regex = Regex::new(r"Logging experiment in directory: (.+)").unwrap();
+    let name_regex = Regex::new(r"Exact experiment name requested from command line:? (.+)").unwrap();
+
+    let directory = directory_regex.captures_iter(log).last()?;
+    let rest = &log[directory.get(0)?.end()..];
+    let name = name_regex.captures(rest)?[1].trim().to_string();
+    // The scripts print absolute paths; a relative one is relative to where the task ran.
+    let directory = working_dir.join(directory[1].trim());
+
+    let exact = directory.join(&name);
+    if exact.is_dir() {
+        return Some(exact);
+    }
+    let prefix = format!("{}_", name);
+    let mut named: Vec<PathBuf> = std::fs::read_dir(&directory)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    named.sort();
+    named.into_iter().next()
+}
+
+fn loaded_run_dir(log: &str, working_dir: &Path) -> Option<PathBuf> {
+    let checkpoint_regex = Regex::new(r"Loading model checkpoint from: (.+)").unwrap();
+    let checkpoint = checkpoint_regex.captures_iter(log).last()?;
+    let run_dir = working_dir.join(checkpoint[1].trim()).parent()?.to_path_buf();
+    run_dir.is_dir().then_some(run_dir)
+}
+
+/// The run directory of a task: the one stored on it, or else the one its event files
+/// point to.
+pub async fn task_run_dir(state: &AppState, task: &Task) -> Result<Option<PathBuf>, AppError> {
+    if let Some(run_dir) = &task.run_dir {
+        return Ok(Some(PathBuf::from(run_dir)));
+    }
+    let Some(started_at) = task.started_at else {
+        return Ok(None);
+    };
+    let working_dir = task.resolved_working_dir(&state.config.read().await.tasks);
+    let finished_at = task.finished_at;
+    tokio::task::spawn_blocking(move || tfevents::find_run_dir(&working_dir, started_at, finished_at))
+        .await
+        .map_err(|e| AppError::Io(std::io::Error::other(e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn training_log(directory: &str) -> String {
+        format!(
+            "[INFO] Logging experiment in directory: {}\n\
+             Exact experiment name requested from command line: 2026-03-02_10-15-42\n\
+             Learning iteration 0/1500\n",
+            directory
+        )
+    }
+
+    #[test]
+    fn finds_the_training_run_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let experiment = dir.path().join("logs/rsl_rl/anymal_d_flat");
+        let log = training_log(&experiment.to_string_lossy());
+        // Printed before the directory is created.
+        assert_eq!(from_log(&log, dir.path()), None);
+
+        std::fs::create_dir_all(experiment.join("2026-03-02_10-15-42")).unwrap();
+        let expected = experiment.join("2026-03-02_10-15-42");
+        assert_eq!(from_log(&log, dir.path()), Some(expected.clone()));
+        // Relative directories are relative to the working directory.
+        let relative = training_log("logs/rsl_rl/anymal_d_flat");
+        assert_eq!(from_log(&relative, dir.path()), Some(expected));
+    }
+
+    #[test]
+    fn finds_a_named_training_run_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let experiment = dir.path().join("logs/rsl_rl/anymal_d_flat");
+        std::fs::create_dir_all(experiment.join("2026-03-02_10-15-42_rough")).unwrap();
+        std::fs::create_dir_all(experiment.join("2026-03-02_09-00-00")).unwrap();
+        let log = training_log(&experiment.to_string_lossy());
+        let expected = experiment.join("2026-03-02_10-15-42_rough");
+        assert_eq!(from_log(&log, dir.path()), Some(expected));
+    }
+
+    #[test]
+    fn finds_the_run_dir_of_a_loaded_checkpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let run_dir = dir.path().join("logs/rsl_rl/anymal_d_flat/2026-03-02_10-15-42");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        let log = format!(
+            "[INFO]: Loading model checkpoint from: {}\n",
+            run_dir.join("model_1499.pt").display()
+        );
+        assert_eq!(from_log(&log, dir.path()), Some(run_dir));
+        assert_eq!(from_log("no paths here\n", dir.path()), None);
+    }
+
+    #[test]
+    fn falls_back_to_event_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let run_dir = dir.path().join("logs/rsl_rl/anymal_d_flat/2026-01-01_00-00-00");
+        std::fs::create_dir_all(&run_dir).unwrap();
+        std::fs::write(run_dir.join("events.out.tfevents.1767225600.host.1.0"), b"").unwrap();
+        let log_path = dir.path().join("task.log");
+        std::fs::write(&log_path, "Learning iteration 0/1500\n").unwrap();
+
+        let started_at = DateTime::from_timestamp(1767225590, 0).unwrap();
+        let finished_at = Some(started_at + chrono::Duration::hours(1));
+        assert_eq!(detect(&log_path, dir.path(), Some(started_at), finished_at), Some(run_dir));
+        // Event files from long before the task started belong to another run.
+        let later = started_at + chrono::Duration::days(1);
+        assert_eq!(detect(&log_path, dir.path(), Some(later), None), None);
+        assert_eq!(detect(&log_path, dir.path(), None, None), None);
+    }
+}
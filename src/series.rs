@@ -0,0 +1,189 @@
+use serde::Serialize;
+
+// --- Metric series helpers ---
+//
+// Series are `(x, y)` points sorted by `x`, as plotted by the metrics charts.
+
+/// Exponential moving average with TensorBoard's debiasing, so the start of the
+/// series is not pulled towards zero. `weight` is in `[0, 1)`; `0` leaves the series
+/// unchanged.
+pub fn ema(points: &[(f64, f64)], weight: f64) -> Vec<(f64, f64)> {
+    let weight = weight.clamp(0.0, 0.999);
+    let mut last = 0.0;
+    let mut accumulated = 0;
+    points
+        .iter()
+        .map(|&(x, y)| {
+            if !y.is_finite() {
+                return (x, y);
+            }
+            last = last * weight + (1.0 - weight) * y;
+            accumulated += 1;
+            let debias = 1.0 - weight.powi(accumulated);
+            (x, last / debias)
+        })
+        .collect()
+}
+
+/// Downsamples a series to at most `threshold` points with Largest-Triangle-Three-
+/// Buckets, which keeps the visual shape (peaks and dips) of the series. A threshold
+/// below 2 leaves the series unchanged, as the first and last points are always kept.
+pub fn lttb(points: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= points.len() || threshold < 2 {
+        return points.to_vec();
+    }
+    if threshold == 2 {
+        return vec![points[0], points[points.len() - 1]];
+    }
+
+    let mut sampled = Vec::with_capacity(threshold);
+    // The first and last points are always kept; the rest is split into buckets.
+    let bucket_size = (points.len() - 2) as f64 / (threshold - 2) as f64;
+    let mut selected = 0;
+    sampled.push(points[0]);
+
+    for bucket in 0..threshold - 2 {
+        let start = (bucket as f64 * bucket_size) as usize + 1;
+        let end = (((bucket + 1) as f64 * bucket_size) as usize + 1).min(points.len() - 1);
+
+        // Average of the next bucket, the third corner of the triangles.
+        let next_start = end;
+        let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(points.len());
+        let next = &points[next_start..next_end.max(next_start + 1)];
+        let avg_x = next.iter().map(|p| p.0).sum::<f64>() / next.len() as f64;
+        let avg_y = next.iter().map(|p| p.1).sum::<f64>() / next.len() as f64;
+
+        let (ax, ay) = points[selected];
+        let mut best = start;
+        let mut best_area = -1.0;
+        for (i, &(bx, by)) in points.iter().enumerate().take(end).skip(start) {
+            let area = ((ax - avg_x) * (by - ay) - (ax - bx) * (avg_y - ay)).abs();
+            if area > best_area {
+                best_area = area;
+                best = i;
+            }
+        }
+        sampled.push(points[best]);
+        selected = best;
+    }
+
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+/// Downsamples a series to at most `threshold` points by keeping the lowest and the
+/// highest point of each bucket, so no spike disappears. Like [`lttb`], it keeps the
+/// first and last points and leaves the series unchanged for a threshold below 2.
+pub fn minmax(points: &[(f64, f64)], threshold: usize) -> Vec<(f64, f64)> {
+    if threshold >= points.len() || threshold < 2 {
+        return points.to_vec();
+    }
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+    let interior = &points[1..points.len() - 1];
+    let count = (threshold - 2) / 2;
+    for bucket in buckets(interior, count).take(count) {
+        let min = bucket.iter().min_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        let max = bucket.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+        if min.0 <= max.0 {
+            sampled.push(*min);
+            sampled.push(*max);
+        } else {
+            sampled.push(*max);
+            sampled.push(*min);
+        }
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled.dedup_by(|a, b| a == b);
+    sampled
+}
+
+/// The range of raw values within one bucket of a series.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct EnvelopeBucket {
+    pub start: f64,
+    pub end: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// Splits a series into at most `count` buckets and returns the value range of each,
+/// for drawing the band around a smoothed or downsampled line.
+pub fn envelope(points: &[(f64, f64)], count: usize) -> Vec<EnvelopeBucket> {
+    buckets(points, count)
+        .map(|bucket| EnvelopeBucket {
+            start: bucket[0].0,
+            end: bucket[bucket.len() - 1].0,
+            min: bucket.iter().map(|p| p.1).fold(f64::INFINITY, f64::min),
+            max: bucket.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max),
+        })
+        .collect()
+}
+
+/// Splits a series into at most `count` non-empty buckets of (nearly) equal size.
+fn buckets(points: &[(f64, f64)], count: usize) -> impl Iterator<Item = &[(f64, f64)]> {
+    let size = points.len().div_ceil(count.max(1)).max(1);
+    points.chunks(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(len: usize) -> Vec<(f64, f64)> {
+        (0..len).map(|i| (i as f64, ((i * 7) % 11) as f64)).collect()
+    }
+
+    #[test]
+    fn threshold_at_or_above_len_keeps_every_point() {
+        let points = series(10);
+        assert_eq!(lttb(&points, 10), points);
+        assert_eq!(lttb(&points, 50), points);
+        assert_eq!(minmax(&points, 10), points);
+        assert_eq!(minmax(&points, 50), points);
+        let envelope = envelope(&points, 50);
+        assert_eq!(envelope.len(), points.len());
+        for (bucket, &(x, y)) in envelope.iter().zip(&points) {
+            assert_eq!(*bucket, EnvelopeBucket { start: x, end: x, min: y, max: y });
+        }
+    }
+
+    #[test]
+    fn threshold_two_keeps_first_and_last() {
+        let points = series(100);
+        let ends = vec![points[0], points[99]];
+        assert_eq!(lttb(&points, 2), ends);
+        assert_eq!(minmax(&points, 2), ends);
+        assert_eq!(minmax(&points, 3), ends);
+        let envelope = envelope(&points, 2);
+        assert_eq!(envelope.len(), 2);
+        assert_eq!((envelope[0].start, envelope[1].end), (0.0, 99.0));
+    }
+
+    #[test]
+    fn downsampling_keeps_first_and_last() {
+        let points = series(100);
+        for threshold in [3, 4, 10, 33, 99] {
+            for sampled in [lttb(&points, threshold), minmax(&points, threshold)] {
+                assert!(sampled.len() <= threshold, "{} points for {}", sampled.len(), threshold);
+                assert_eq!(sampled.first(), points.first());
+                assert_eq!(sampled.last(), points.last());
+                assert!(sampled.windows(2).all(|w| w[0].0 < w[1].0));
+            }
+            let envelope = envelope(&points, threshold);
+            assert!(envelope.len() <= threshold);
+            assert_eq!(envelope[0].start, 0.0);
+            assert_eq!(envelope[envelope.len() - 1].end, 99.0);
+        }
+    }
+
+    #[test]
+    fn minmax_keeps_spikes() {
+        let mut points = series(100);
+        points[37].1 = 1000.0;
+        points[62].1 = -1000.0;
+        let sampled = minmax(&points, 10);
+        assert!(sampled.contains(&points[37]));
+        assert!(sampled.contains(&points[62]));
+    }
+}
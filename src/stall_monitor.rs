@@ -0,0 +1,347 @@
+use std::{
+    collections::HashMap,
+    io::SeekFrom,
+    path::Path,
+    time::{Duration, Instant, SystemTime},
+};
+
+use regex::Regex;
+use tokio::{
+    fs as tokio_fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tracing::{error, info, warn};
+
+use crate::{
+    models::{AppState, Task, TaskStatus},
+    notifications::Notification,
+    task_manager,
+};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(10);
+/// How much of the end of the log is scanned for the latest `Learning iteration` line.
+const TAIL_SCAN_BYTES: u64 = 64 * 1024;
+
+/// What the monitor last observed for a running task.
+struct Progress {
+    log_modified: Option<SystemTime>,
+    iteration: Option<i64>,
+    last_output_at: Instant,
+    last_iteration_at: Instant,
+    stalled: bool,
+}
+
+impl Progress {
+    fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            log_modified: None,
+            iteration: None,
+            last_output_at: now,
+            last_iteration_at: now,
+            stalled: false,
+        }
+    }
+
+    /// Seconds since the task last showed any sign of life. Once training has
+    /// printed its first iteration, a log that keeps growing without new
+    /// iterations (e.g. a warning loop) also counts as idle.
+    fn idle_secs(&self) -> u64 {
+        let output_idle = self.last_output_at.elapsed();
+        let idle = match self.iteration {
+            Some(_) => output_idle.max(self.last_iteration_at.elapsed()),
+            None => output_idle,
+        };
+        idle.as_secs()
+    }
+}
+
+// --- Stall Monitor Background Service ---
+
+pub struct StallMonitor {
+    state: AppState,
+    progress: HashMap<String, Progress>,
+    iteration_regex: Regex,
+}
+
+impl StallMonitor {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            progress: HashMap::new(),
+            iteration_regex: Regex::new(r"Learning iteration (\d+)/\d+").unwrap(),
+        }
+    }
+
+    pub async fn run(mut self) {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            self.check_running_tasks().await;
+        }
+    }
+
+    async fn check_running_tasks(&mut self) {
+        let (timeout_secs, kill_on_stall, max_retries) = {
+            let config = self.state.config.read().await;
+            (
+                config.tasks.stall_timeout_secs,
+                config.tasks.kill_on_stall,
+                config.tasks.stall_max_retries,
+            )
+        };
+
+        let running: Vec<Task> = self
+            .state
+            .tasks
+            .read()
+            .await
+            .values()
+            .map(|info| info.task.clone())
+            .collect();
+        self.progress
+            .retain(|id, _| running.iter().any(|task| &task.id == id));
+
+        if timeout_secs == 0 {
+            return;
+        }
+
+        for task in running {
+            let Some(log_path) = task.log_path.clone() else {
+                continue;
+            };
+            let iteration = self.latest_iteration(Path::new(&log_path)).await;
+            let log_modified = tokio_fs::metadata(&log_path)
+                .await
+                .and_then(|m| m.modified())
+                .ok();
+
+            let progress = self
+                .progress
+                .entry(task.id.clone())
+                .or_insert_with(Progress::new);
+            if log_modified != progress.log_modified {
+                progress.log_modified = log_modified;
+                progress.last_output_at = Instant::now();
+            }
+            if iteration.is_some() && iteration != progress.iteration {
+                progress.iteration = iteration;
+                progress.last_iteration_at = Instant::now();
+            }
+
+            let idle_secs = progress.idle_secs();
+            let is_stalled = idle_secs >= timeout_secs;
+            if is_stalled == progress.stalled {
+                continue;
+            }
+            progress.stalled = is_stalled;
+
+            if is_stalled {
+                warn!("Task {} has been idle for {} seconds, flagging as stalled.", task.id, idle_secs);
+                Self::set_stalled_at(&self.state, &task.id, Some(chrono::Utc::now())).await;
+                self.state
+                    .notifications
+                    .send(Notification::task_stalled(&task.name, &task.id, idle_secs));
+                if kill_on_stall {
+                    self.progress.remove(&task.id);
+                    Self::kill_stalled_task(&self.state, &task, max_retries).await;
+                }
+            } else {
+                info!("Task {} is producing output again, clearing stalled flag.", task.id);
+                Self::set_stalled_at(&self.state, &task.id, None).await;
+            }
+        }
+    }
+
+    /// Returns the most recent iteration number printed near the end of the log.
+    async fn latest_iteration(&self, log_path: &Path) -> Option<i64> {
+        let mut file = tokio_fs::File::open(log_path).await.ok()?;
+        let len = file.metadata().await.ok()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL_SCAN_BYTES)))
+            .await
+            .ok()?;
+        let mut buffer = Vec::new();
+        file.read_to_end(&mut buffer).await.ok()?;
+        let tail = String::from_utf8_lossy(&buffer);
+        self.iteration_regex
+            .captures_iter(&tail)
+            .last()
+            .and_then(|captures| captures[1].parse().ok())
+    }
+
+    async fn set_stalled_at(
+        state: &AppState,
+        task_id: &str,
+        stalled_at: Option<chrono::DateTime<chrono::Utc>>,
+    ) {
+        if let Err(e) = sqlx::query("UPDATE tasks SET stalled_at = ? WHERE id = ?")
+            .bind(stalled_at)
+            .bind(task_id)
+            .execute(&state.db)
+            .await
+        {
+            error!("Failed to update stalled flag of task {}: {}", task_id, e);
+        }
+    }
+
+    /// Kills a stalled task and either re-queues it or marks it as failed once
+    /// `tasks.stall_max_retries` has been used up.
+    async fn kill_stalled_task(state: &AppState, task: &Task, max_retries: u32) {
+        // Removing the task from the live map stops the `wait` task from recording a final status.
+        state.tasks.write().await.remove(&task.id);
+        if let Some(pid) = task.pid {
+            task_manager::kill_process_group(pid);
+        }
+
+        if task.retry_count < max_retries as i64 {
+            // Keep the log of the stalled attempt around for diagnosis.
+            if let Some(log_path) = &task.log_path {
+                let attempt_log = format!("{}.{}", log_path, task.retry_count);
+                if let Err(e) = tokio_fs::rename(log_path, &attempt_log).await {
+                    warn!("Failed to preserve log of stalled task {}: {}", task.id, e);
+                }
+            }
+
+            let result = sqlx::query(
+                "UPDATE tasks SET status = ?, retry_count = retry_count + 1, stalled_at = NULL, pid = NULL, started_at = NULL, run_dir = NULL,
+                 current_iteration = NULL, total_iterations = NULL, progress_pct = NULL, steps_per_sec = NULL, eta_seconds = NULL,
+                 latest_fixed_metrics = NULL WHERE id = ?",
+            )
+            .bind(TaskStatus::Queued)
+            .bind(&task.id)
+            .execute(&state.db)
+            .await;
+            match result {
+                Ok(_) => {
+                    info!(
+                        "Re-queued stalled task {} (retry {}/{}).",
+                        task.id,
+                        task.retry_count + 1,
+                        max_retries
+                    );
+                    state.queue.lock().await.push(task.id.clone());
+                }
+                Err(e) => error!("Failed to re-queue stalled task {}: {}", task.id, e),
+            }
+        } else {
+            // A stall without an error in the log is its own diagnosis.
+            let diagnosis = match &task.log_path {
+                Some(log_path) => task_manager::diagnose_failure(state, Path::new(log_path)).await,
+                None => None,
+            };
+            let (category, excerpt) = match diagnosis {
+                Some(diagnosis) => (diagnosis.category, Some(diagnosis.excerpt)),
+                None => ("stalled".to_string(), None),
+            };
+            if let Err(e) = sqlx::query(
+                "UPDATE tasks SET status = ?, finished_at = ?, failure_category = ?, failure_excerpt = ? WHERE id = ?",
+            )
+            .bind(TaskStatus::Failed)
+            .bind(chrono::Utc::now())
+            .bind(&category)
+            .bind(&excerpt)
+            .bind(&task.id)
+            .execute(&state.db)
+            .await
+            {
+                error!("Failed to mark stalled task {} as failed: {}", task.id, e);
+            }
+            info!("Stalled task {} was killed and marked as failed.", task.id);
+            state.metrics.increment_tasks_failed();
+            state.notifications.send(Notification::task_failed(
+                &task.name,
+                &task.id,
+                &category,
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::Config, models::TaskInfo, notifications::NotificationType};
+
+    async fn insert_task(state: &AppState, log_path: &Path) -> Task {
+        sqlx::query(
+            "INSERT INTO tasks (id, name, command, status, created_at, started_at, log_path, current_iteration,
+             total_iterations, progress_pct, eta_seconds, latest_fixed_metrics)
+             VALUES ('task', 'train', 'python train.py', ?, ?, ?, ?, 120, 1500, 8.0, 600.0, '{\"Mean reward\":\"3.5\"}')",
+        )
+        .bind(TaskStatus::Running)
+        .bind(chrono::Utc::now())
+        .bind(chrono::Utc::now())
+        .bind(log_path.to_string_lossy())
+        .execute(&state.db)
+        .await
+        .unwrap();
+        let task = fetch_task(state).await;
+        let info = TaskInfo { task: task.clone() };
+        state.tasks.write().await.insert(task.id.clone(), info);
+        task
+    }
+
+    async fn fetch_task(state: &AppState) -> Task {
+        sqlx::query_as("SELECT * FROM tasks WHERE id = 'task'")
+            .fetch_one(&state.db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn flags_idle_tasks_and_clears_the_flag_on_new_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("task.log");
+        std::fs::write(&log_path, "Learning iteration 1/1500\n").unwrap();
+        let mut config = Config::default();
+        config.tasks.stall_timeout_secs = 1;
+        let state = AppState::for_tests(config).await;
+        insert_task(&state, &log_path).await;
+        let mut notifications = state.notifications.subscribe();
+        let mut monitor = StallMonitor::new(state.clone());
+
+        monitor.check_running_tasks().await;
+        assert!(fetch_task(&state).await.stalled_at.is_none());
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        monitor.check_running_tasks().await;
+        assert!(fetch_task(&state).await.stalled_at.is_some());
+        let notification = notifications.try_recv().unwrap();
+        assert!(matches!(notification.notification_type, NotificationType::TaskStalled));
+        // Flagged but not killed.
+        assert!(state.tasks.read().await.contains_key("task"));
+
+        std::fs::write(&log_path, "Learning iteration 2/1500\n").unwrap();
+        monitor.check_running_tasks().await;
+        assert!(fetch_task(&state).await.stalled_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn requeues_a_stalled_task_until_its_retries_are_used_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("task.log");
+        std::fs::write(&log_path, "Learning iteration 120/1500\n").unwrap();
+        let state = AppState::for_tests(Config::default()).await;
+        let task = insert_task(&state, &log_path).await;
+
+        StallMonitor::kill_stalled_task(&state, &task, 1).await;
+        let requeued = fetch_task(&state).await;
+        assert!(matches!(requeued.status, TaskStatus::Queued));
+        assert_eq!(requeued.retry_count, 1);
+        assert!(requeued.started_at.is_none());
+        // Nothing of the stalled attempt's progress carries over to the retry.
+        assert!(requeued.current_iteration.is_none());
+        assert!(requeued.total_iterations.is_none());
+        assert!(requeued.progress_pct.is_none());
+        assert!(requeued.eta_seconds.is_none());
+        assert!(requeued.latest_fixed_metrics.is_none());
+        assert_eq!(*state.queue.lock().await, vec!["task".to_string()]);
+        assert!(!state.tasks.read().await.contains_key("task"));
+        assert!(dir.path().join("task.log.0").exists());
+
+        StallMonitor::kill_stalled_task(&state, &requeued, 1).await;
+        let failed = fetch_task(&state).await;
+        assert!(matches!(failed.status, TaskStatus::Failed));
+        assert_eq!(failed.failure_category.as_deref(), Some("stalled"));
+        assert_eq!(state.queue.lock().await.len(), 1);
+    }
+}
@@ -1,10 +1,19 @@
 use anyhow::Result;
-use nix::unistd::setsid;
-use std::sync::Arc;
-use tokio::{fs as tokio_fs, process::Command, sync::Mutex};
-use tracing::{error, info};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::{setsid, Pid},
+};
+use std::{collections::HashSet, path::PathBuf};
+use tokio::{fs as tokio_fs, process::Command};
+use tracing::{error, info, warn};
 
-use crate::models::{AppState, Task, TaskInfo, TaskStatus};
+use crate::{
+    evaluation,
+    failure_classifier::{Diagnosis, FailureClassifier},
+    models::{AppState, Task, TaskInfo, TaskStatus},
+    notifications::Notification,
+    routes::sync::{build_manifest, manifest_hash},
+};
 
 // --- Task Manager Background Service ---
 
@@ -18,24 +27,44 @@ impl TaskManager {
     }
 
     pub async fn run(self) {
+        // The start of the last task taken off the queue, which is not yet among the
+        // running tasks until it has been spawned.
+        let mut starting: Option<tokio::task::JoinHandle<()>> = None;
         loop {
-            let task_id = self.get_next_task_from_queue().await;
+            let start_pending = starting.as_ref().is_some_and(|start| !start.is_finished());
+            let task_id = self.get_next_task_from_queue(start_pending).await;
 
             if let Some(task_id) = task_id {
                 let state = self.state.clone();
-                tokio::spawn(async move {
+                starting = Some(tokio::spawn(async move {
                     if let Err(e) = Self::execute_task(state, &task_id).await {
                         error!("Failed to execute task {}: {}", task_id, e);
                     }
-                });
+                }));
             }
             tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
     }
 
-    async fn get_next_task_from_queue(&self) -> Option<String> {
+    /// Takes the next task off the queue, see [`next_in_queue`].
+    async fn get_next_task_from_queue(&self, start_pending: bool) -> Option<String> {
         let mut queue = self.state.queue.lock().await;
-        queue.pop()
+        let low_priority: HashSet<String> = match sqlx::query_scalar(
+            "SELECT id FROM tasks WHERE status = ? AND eval_source_task_id IS NOT NULL",
+        )
+        .bind(TaskStatus::Queued)
+        .fetch_all(&self.state.db)
+        .await
+        {
+            Ok(ids) => ids.into_iter().collect(),
+            Err(e) => {
+                error!("Failed to look up low-priority tasks: {}", e);
+                return None;
+            }
+        };
+        let busy = start_pending || !self.state.tasks.read().await.is_empty();
+        let position = next_in_queue(&queue, &low_priority, busy)?;
+        Some(queue.remove(position))
     }
 
     async fn execute_task(state: AppState, task_id: &str) -> Result<()> {
@@ -73,6 +102,19 @@ impl TaskManager {
                 .to_string()
         });
 
+        let code_dir = PathBuf::from(&working_dir);
+        let excludes = config.sync.default_excludes.clone();
+        let code_hash = tokio::task::spawn_blocking(move || {
+            code_dir
+                .is_dir()
+                .then(|| manifest_hash(&build_manifest(&code_dir, &excludes)))
+        })
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to hash the code of task {}: {}", task_id, e);
+            None
+        });
+
         let mut cmd = Command::new("bash");
         cmd.current_dir(&working_dir)
             .arg("-c")
@@ -84,13 +126,13 @@ impl TaskManager {
         unsafe {
             cmd.pre_exec(|| {
                 setsid().map_err(|e| {
-                    std::io::Error::new(std::io::ErrorKind::Other, format!("setsid failed: {}", e))
+                    std::io::Error::other(format!("setsid failed: {}", e))
                 })?;
                 Ok(())
             });
         }
 
-        let child = cmd.spawn()?;
+        let mut child = cmd.spawn()?;
         let pid = child.id().map(|id| id as i64);
 
         let now = chrono::Utc::now();
@@ -100,38 +142,42 @@ impl TaskManager {
         task.started_at = Some(now);
         task.log_path = log_path_str.clone();
         task.pid = pid;
+        task.code_hash = code_hash;
 
-        if let Err(e) =
-            sqlx::query("UPDATE tasks SET status = ?, started_at = ?, log_path = ?, pid = ? WHERE id = ?")
-                .bind(task.status)
-                .bind(task.started_at)
-                .bind(&task.log_path)
-                .bind(task.pid)
-                .bind(task_id)
-                .execute(&state.db)
-                .await
+        if let Err(e) = sqlx::query(
+            "UPDATE tasks SET status = ?, started_at = ?, log_path = ?, pid = ?, code_hash = ? WHERE id = ?",
+        )
+        .bind(task.status)
+        .bind(task.started_at)
+        .bind(&task.log_path)
+        .bind(task.pid)
+        .bind(&task.code_hash)
+        .bind(task_id)
+        .execute(&state.db)
+        .await
         {
             error!("Failed to update task {} to running state: {}", task_id, e);
             // If we can't update the DB, we shouldn't proceed.
             return Err(e.into());
         }
 
-        let child_arc = Arc::new(Mutex::new(child));
-
-        let task_info = TaskInfo {
-            task: task.clone(),
-            process: Some(child_arc.clone()),
-        };
+        let task_info = TaskInfo { task: task.clone() };
         state
             .tasks
             .write()
             .await
             .insert(task_id.to_string(), task_info);
+        state
+            .notifications
+            .send(Notification::task_started(&task.name, task_id));
 
         let wait_state = state.clone();
         let wait_task_id = task_id.to_string();
+        let wait_task = task.clone();
+        let wait_task_name = task.name.clone();
+        let wait_log_path = log_path.clone();
         tokio::spawn(async move {
-            let status = match child_arc.lock().await.wait().await {
+            let status = match child.wait().await {
                 Ok(status) => status,
                 Err(e) => {
                     error!("Failed to wait for task {}: {}", wait_task_id, e);
@@ -139,23 +185,41 @@ impl TaskManager {
                 }
             };
 
-            // The task might have been stopped manually. If so, it will be removed from the map.
-            // If we can remove it, it means it finished naturally.
-            if let Some(_removed_task) = wait_state.tasks.write().await.remove(&wait_task_id) {
+            // The task might have been stopped manually or killed for stalling. If so, it
+            // will have been removed from the map, and a re-queued task may already have
+            // been started again under the same ID. Only the entry of this very run means
+            // it finished naturally.
+            let finished_naturally = {
+                let mut tasks = wait_state.tasks.write().await;
+                let is_this_run = tasks.get(&wait_task_id).is_some_and(|info| {
+                    info.task.started_at == wait_task.started_at && info.task.pid == wait_task.pid
+                });
+                is_this_run && tasks.remove(&wait_task_id).is_some()
+            };
+            if finished_naturally {
                 let final_status = if status.success() {
                     TaskStatus::Completed
                 } else {
                     TaskStatus::Failed
                 };
                 let finished_at = chrono::Utc::now();
+                let diagnosis = if final_status == TaskStatus::Failed {
+                    diagnose_failure(&wait_state, &wait_log_path).await
+                } else {
+                    None
+                };
 
-                if let Err(e) =
-                    sqlx::query("UPDATE tasks SET status = ?, finished_at = ? WHERE id = ?")
-                        .bind(final_status)
-                        .bind(finished_at)
-                        .bind(&wait_task_id)
-                        .execute(&state.db)
-                        .await
+                if let Err(e) = sqlx::query(
+                    "UPDATE tasks SET status = ?, finished_at = ?, exit_code = ?, failure_category = ?, failure_excerpt = ? WHERE id = ?",
+                )
+                .bind(final_status)
+                .bind(finished_at)
+                .bind(status.code())
+                .bind(diagnosis.as_ref().map(|d| &d.category))
+                .bind(diagnosis.as_ref().map(|d| &d.excerpt))
+                .bind(&wait_task_id)
+                .execute(&state.db)
+                .await
                 {
                     error!(
                         "Failed to update task {} status after completion: {}",
@@ -166,11 +230,26 @@ impl TaskManager {
                     "Task {} finished with status: {:?}",
                     wait_task_id, final_status
                 );
+                if let Err(e) = evaluation::record_results(&wait_state, &wait_task, &wait_log_path).await {
+                    error!("Failed to record evaluation results of task {}: {}", wait_task_id, e);
+                }
+                let notification = if final_status == TaskStatus::Completed {
+                    wait_state.metrics.increment_tasks_completed();
+                    Notification::task_completed(&wait_task_name, &wait_task_id)
+                } else {
+                    wait_state.metrics.increment_tasks_failed();
+                    let reason = match &diagnosis {
+                        Some(diagnosis) => format!("{} ({})", status, diagnosis.category),
+                        None => status.to_string(),
+                    };
+                    Notification::task_failed(&wait_task_name, &wait_task_id, &reason)
+                };
+                wait_state.notifications.send(notification);
             } else {
-                // If the task was not in the map, it means it was stopped via the API.
-                // The stop_task_handler is responsible for updating the DB in this case.
+                // The run was stopped via the API or killed by the stall monitor, which
+                // are responsible for updating the DB in this case.
                 info!(
-                    "Task {} was stopped manually, skipping final status update.",
+                    "Task {} was stopped or killed, skipping final status update.",
                     wait_task_id
                 );
             }
@@ -179,3 +258,85 @@ impl TaskManager {
         Ok(())
     }
 }
+
+/// Position of the next task to start in `queue`, which is served from its end.
+/// Low-priority tasks, the evaluations, are held back while any other task is queued
+/// and only start while no task is running or starting (`busy`), so at most one
+/// evaluation runs at a time and evaluations never share the GPU with a training run.
+fn next_in_queue(queue: &[String], low_priority: &HashSet<String>, busy: bool) -> Option<usize> {
+    match queue.iter().rposition(|id| !low_priority.contains(id)) {
+        Some(position) => Some(position),
+        None if busy => None,
+        None => queue.len().checked_sub(1),
+    }
+}
+
+/// Classifies the failure of a task from the end of its log, using the configured
+/// failure rules on top of the built-in ones.
+pub async fn diagnose_failure(state: &AppState, log_path: &std::path::Path) -> Option<Diagnosis> {
+    let rules = state.config.read().await.tasks.failure_rules.clone();
+    let diagnosis = FailureClassifier::new(&rules).classify_log(log_path).await;
+    if let Some(diagnosis) = &diagnosis {
+        info!("Classified failure in {:?} as {}", log_path, diagnosis.category);
+    }
+    diagnosis
+}
+
+/// Sends SIGKILL to the whole process group of a task. Tasks are started with `setsid`,
+/// so the group ID equals the PID of the spawned shell.
+pub fn kill_process_group(pid: i64) {
+    if pid <= 0 {
+        return;
+    }
+    info!("Attempting to stop process group with PID: {}", pid);
+    // Use nix to kill the entire process group by passing a negative PID.
+    let pgid = Pid::from_raw(-pid as i32);
+    match signal::kill(pgid, Signal::SIGKILL) {
+        Ok(_) => info!("Successfully sent SIGKILL to process group {}", pid),
+        Err(e) => {
+            // It's not a critical error if the process doesn't exist (e.g., it already finished)
+            // We can just log a warning.
+            warn!(
+                "Failed to kill process group {}: {}. This might be because the process already stopped.",
+                pid, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Takes tasks off the queue until only held-back ones are left.
+    fn drain(queue: &mut Vec<String>, low_priority: &HashSet<String>, busy: bool) -> Vec<String> {
+        let mut started = Vec::new();
+        while let Some(position) = next_in_queue(queue, low_priority, busy) {
+            started.push(queue.remove(position));
+        }
+        started
+    }
+
+    #[test]
+    fn evaluations_start_last_and_one_at_a_time() {
+        // Evaluations are inserted at the front, other tasks pushed to the end.
+        let mut queue: Vec<String> = ["eval-2", "eval-1", "train-1", "train-2"].map(String::from).to_vec();
+        let low_priority: HashSet<String> = ["eval-1", "eval-2"].map(String::from).into();
+
+        // Training runs start whether or not something is running.
+        assert_eq!(drain(&mut queue, &low_priority, true), ["train-2", "train-1"]);
+        assert_eq!(queue, ["eval-2", "eval-1"]);
+
+        // Evaluations wait for the running task, then start one per idle slot.
+        assert_eq!(next_in_queue(&queue, &low_priority, true), None);
+        assert_eq!(next_in_queue(&queue, &low_priority, false), Some(1));
+        queue.remove(1);
+        assert_eq!(next_in_queue(&queue, &low_priority, true), None);
+
+        // A training run queued meanwhile goes ahead of the remaining evaluation.
+        queue.push("train-3".to_string());
+        assert_eq!(drain(&mut queue, &low_priority, true), ["train-3"]);
+        assert_eq!(drain(&mut queue, &low_priority, false), ["eval-2"]);
+        assert_eq!(next_in_queue(&queue, &low_priority, false), None);
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use nix::unistd::setsid;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use tokio::{net::TcpStream, process::{Child, Command}, sync::Mutex};
+use tracing::{error, info, warn};
+
+use crate::{
+    error::AppError,
+    models::{AppState, Task},
+    run_dir, task_manager,
+};
+
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+/// How long a freshly launched TensorBoard gets to start listening.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A TensorBoard process serving one task's runs.
+struct Instance {
+    child: Child,
+    port: u16,
+    logdir: PathBuf,
+    last_access: Instant,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TensorboardInfo {
+    pub port: u16,
+    pub logdir: PathBuf,
+    /// Path under which the manager proxies the instance.
+    pub url: String,
+}
+
+impl Instance {
+    fn info(&self, task_id: &str) -> TensorboardInfo {
+        TensorboardInfo {
+            port: self.port,
+            logdir: self.logdir.clone(),
+            url: format!("{}/", path_prefix(task_id)),
+        }
+    }
+
+    fn kill(&mut self) {
+        if let Some(pid) = self.child.id() {
+            task_manager::kill_process_group(pid as i64);
+        }
+    }
+}
+
+/// The path TensorBoard is told it is served under, so its links go through the proxy.
+pub fn path_prefix(task_id: &str) -> String {
+    format!("/tensorboard/{}", task_id)
+}
+
+#[derive(Default)]
+struct Instances {
+    running: HashMap<String, Instance>,
+    /// Ports reserved by instances that are still starting up, by task.
+    starting: HashMap<String, u16>,
+}
+
+/// Launches, tracks and stops the TensorBoard instances of tasks.
+#[derive(Clone, Default)]
+pub struct TensorboardManager {
+    instances: Arc<Mutex<Instances>>,
+    client: reqwest::Client,
+}
+
+impl TensorboardManager {
+    /// HTTP client used to proxy requests to the instances.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
+    }
+
+    /// Returns the running instance of a task, launching one if needed. The lock on
+    /// the instances is only held to reserve a port, not while TensorBoard starts up.
+    pub async fn start(&self, state: &AppState, task: &Task) -> Result<TensorboardInfo, AppError> {
+        let config = state.config.read().await.clone();
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        let port = loop {
+            {
+                let mut instances = self.instances.lock().await;
+                if let Some(instance) = instances.running.get_mut(&task.id) {
+                    if matches!(instance.child.try_wait(), Ok(None)) {
+                        instance.last_access = Instant::now();
+                        return Ok(instance.info(&task.id));
+                    }
+                    instances.running.remove(&task.id);
+                }
+                if !instances.starting.contains_key(&task.id) {
+                    let port = (config.tensorboard.port_range_start..=config.tensorboard.port_range_end)
+                        .find(|port| {
+                            !instances.running.values().any(|instance| instance.port == *port)
+                                && !instances.starting.values().any(|starting| starting == port)
+                                && port_is_free(*port)
+                        })
+                        .ok_or_else(|| {
+                            AppError::CommandFailed("No free port left in the TensorBoard port range".to_string())
+                        })?;
+                    instances.starting.insert(task.id.clone(), port);
+                    break port;
+                }
+            }
+            // Another request is launching this task's instance; wait for it to be published.
+            if Instant::now() >= deadline {
+                return Err(AppError::CommandFailed("TensorBoard is still starting".to_string()));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        };
+
+        // Launched in its own task so that a dropped request cannot leave the port reserved.
+        let manager = self.clone();
+        let state = state.clone();
+        let task = task.clone();
+        tokio::spawn(async move {
+            let launched = launch(&state, &task, port).await;
+            let mut instances = manager.instances.lock().await;
+            instances.starting.remove(&task.id);
+            let instance = launched?;
+            let info = instance.info(&task.id);
+            instances.running.insert(task.id.clone(), instance);
+            Ok(info)
+        })
+        .await
+        .map_err(|e| AppError::CommandFailed(format!("TensorBoard launch failed: {}", e)))?
+    }
+
+    /// Returns the port of a task's instance and records the access.
+    pub async fn touch(&self, task_id: &str) -> Option<u16> {
+        let mut instances = self.instances.lock().await;
+        let instance = instances.running.get_mut(task_id)?;
+        instance.last_access = Instant::now();
+        Some(instance.port)
+    }
+
+    pub async fn info(&self, task_id: &str) -> Option<TensorboardInfo> {
+        let instances = self.instances.lock().await;
+        instances.running.get(task_id).map(|instance| instance.info(task_id))
+    }
+
+    /// Stops a task's instance. Returns false if it had none.
+    pub async fn stop(&self, db: &SqlitePool, task_id: &str) -> Result<bool, sqlx::Error> {
+        let instance = self.instances.lock().await.running.remove(task_id);
+        let Some(mut instance) = instance else {
+            return Ok(false);
+        };
+        instance.kill();
+        let _ = instance.child.wait().await;
+        set_port(db, task_id, None).await?;
+        info!("Stopped TensorBoard for task {}", task_id);
+        Ok(true)
+    }
+
+    /// Stops instances that exited on their own or have not been used for `idle_timeout`.
+    async fn reap(&self, db: &SqlitePool, idle_timeout: Duration) -> Result<(), sqlx::Error> {
+        let mut reaped = Vec::new();
+        {
+            let mut instances = self.instances.lock().await;
+            instances.running.retain(|task_id, instance| {
+                let exited = !matches!(instance.child.try_wait(), Ok(None));
+                let idle = instance.last_access.elapsed() >= idle_timeout;
+                if exited || idle {
+                    if !exited {
+                        instance.kill();
+                    }
+                    reaped.push(task_id.clone());
+                }
+                !(exited || idle)
+            });
+        }
+        for task_id in reaped {
+            info!("Shut down idle or exited TensorBoard of task {}", task_id);
+            set_port(db, &task_id, None).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn set_port(db: &SqlitePool, task_id: &str, port: Option<u16>) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE tasks SET tensorboard_port = ? WHERE id = ?")
+        .bind(port.map(i64::from))
+        .bind(task_id)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// Launches TensorBoard on a reserved port and waits until it is listening.
+async fn launch(state: &AppState, task: &Task, port: u16) -> Result<Instance, AppError> {
+    let config = state.config.read().await.clone();
+    let logdir = run_dir::task_run_dir(state, task).await?.ok_or_else(|| {
+        AppError::BadRequest("No TensorBoard event files found for this task".to_string())
+    })?;
+
+    let conda_env = task
+        .conda_env
+        .clone()
+        .unwrap_or_else(|| config.isaaclab.default_conda_env.clone());
+    let mut cmd = Command::new(config.isaaclab.conda_path.join("bin/conda"));
+    cmd.args(["run", "--no-capture-output", "-n", &conda_env, "tensorboard", "--logdir"])
+        .arg(&logdir)
+        .args(["--host", "127.0.0.1", "--port", &port.to_string()])
+        .args(["--path_prefix", &path_prefix(&task.id)])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true);
+    // Run in its own process group so that conda's child processes are stopped with it.
+    unsafe {
+        cmd.pre_exec(|| {
+            setsid().map_err(|e| std::io::Error::other(format!("setsid failed: {}", e)))?;
+            Ok(())
+        });
+    }
+    let child = cmd
+        .spawn()
+        .map_err(|e| AppError::CommandFailed(format!("Failed to launch TensorBoard: {}", e)))?;
+    let mut instance = Instance {
+        child,
+        port,
+        logdir,
+        last_access: Instant::now(),
+    };
+
+    let deadline = Instant::now() + STARTUP_TIMEOUT;
+    loop {
+        if TcpStream::connect(("127.0.0.1", port)).await.is_ok() {
+            break;
+        }
+        if let Ok(Some(status)) = instance.child.try_wait() {
+            return Err(AppError::CommandFailed(format!(
+                "TensorBoard exited during startup ({})",
+                status
+            )));
+        }
+        if Instant::now() >= deadline {
+            instance.kill();
+            return Err(AppError::CommandFailed(
+                "TensorBoard did not start listening in time".to_string(),
+            ));
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+
+    set_port(&state.db, &task.id, Some(port)).await?;
+    info!(
+        "Started TensorBoard for task {} on port {} ({})",
+        task.id,
+        port,
+        instance.logdir.display()
+    );
+    Ok(instance)
+}
+
+fn port_is_free(port: u16) -> bool {
+    std::net::TcpListener::bind(("127.0.0.1", port)).is_ok()
+}
+
+// --- TensorBoard Reaper Background Service ---
+
+/// Shuts down TensorBoard instances nobody has looked at for a while.
+pub struct TensorboardReaper {
+    state: AppState,
+}
+
+impl TensorboardReaper {
+    pub fn new(state: AppState) -> Self {
+        Self { state }
+    }
+
+    pub async fn run(self) {
+        // Instances launched by a previous run of the manager are no longer tracked.
+        if let Err(e) = sqlx::query("UPDATE tasks SET tensorboard_port = NULL")
+            .execute(&self.state.db)
+            .await
+        {
+            warn!("Failed to clear stale TensorBoard ports: {}", e);
+        }
+        loop {
+            tokio::time::sleep(REAP_INTERVAL).await;
+            let idle_timeout = Duration::from_secs(
+                self.state.config.read().await.tensorboard.idle_timeout_secs,
+            );
+            if let Err(e) = self.state.tensorboard.reap(&self.state.db, idle_timeout).await {
+                error!("Failed to reap TensorBoard instances: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,391 @@
+use std::borrow::Cow;
+
+use serde::{Deserialize, Serialize, Serializer};
+
+// --- Terminal output rendering ---
+//
+// Isaac Sim and tqdm draw progress bars by rewinding the line with `\r` and colour
+// their output with ANSI escape sequences. The helpers below replay a log line on a
+// minimal one-line terminal so it can be shown (or parsed) the way it looked on screen.
+
+/// What to do with ANSI escape sequences when rendering log lines.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnsiMode {
+    /// Leave escape sequences untouched.
+    #[default]
+    Keep,
+    /// Remove all escape sequences.
+    Strip,
+    /// Convert colours and text attributes into structured spans.
+    Spans,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderOptions {
+    pub collapse_cr: bool,
+    pub ansi: AnsiMode,
+}
+
+impl RenderOptions {
+    /// Renders a line as text. Spans are not representable as text, so `Spans` strips.
+    pub fn render_text(&self, line: String) -> String {
+        match self.ansi {
+            AnsiMode::Keep if self.collapse_cr => collapse_keeping_ansi(&line).to_string(),
+            AnsiMode::Keep => line,
+            AnsiMode::Strip | AnsiMode::Spans => render_plain(&line, self.collapse_cr).into_owned(),
+        }
+    }
+
+    /// Renders lines for a JSON response: strings, or arrays of spans for `Spans`.
+    pub fn render_json(&self, lines: Vec<String>) -> serde_json::Value {
+        match self.ansi {
+            AnsiMode::Spans => serde_json::json!(lines
+                .iter()
+                .map(|line| render_spans(line, self.collapse_cr))
+                .collect::<Vec<_>>()),
+            _ => serde_json::json!(lines
+                .into_iter()
+                .map(|line| self.render_text(line))
+                .collect::<Vec<_>>()),
+        }
+    }
+}
+
+/// Prepares log text for the metric parsers: carriage-return overwrites are collapsed
+/// and escape sequences removed, line by line.
+pub fn normalize(text: &str) -> Cow<'_, str> {
+    if !text.contains(['\r', '\x1b']) {
+        return Cow::Borrowed(text);
+    }
+    let mut normalized = String::with_capacity(text.len());
+    for line in text.split_inclusive('\n') {
+        let (content, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+        normalized.push_str(&render_plain(content, true));
+        normalized.push_str(newline);
+    }
+    Cow::Owned(normalized)
+}
+
+/// Renders a line as plain text, optionally replaying `\r` overwrites.
+pub fn render_plain(line: &str, collapse_cr: bool) -> Cow<'_, str> {
+    if !line.contains(['\r', '\x1b', '\x08']) {
+        return Cow::Borrowed(line);
+    }
+    Cow::Owned(replay(line, collapse_cr).into_iter().map(|cell| cell.ch).collect())
+}
+
+/// Renders a line as runs of identically styled text.
+pub fn render_spans(line: &str, collapse_cr: bool) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    for cell in replay(line, collapse_cr) {
+        match spans.last_mut() {
+            Some(span) if span.style == cell.style => span.text.push(cell.ch),
+            _ => spans.push(Span {
+                text: cell.ch.to_string(),
+                style: cell.style,
+            }),
+        }
+    }
+    spans
+}
+
+/// Collapses `\r` overwrites without interpreting escape sequences: progress bars
+/// redraw the whole line, so only the last non-empty segment is kept.
+fn collapse_keeping_ansi(line: &str) -> &str {
+    line.rsplit('\r')
+        .find(|segment| !segment.is_empty())
+        .unwrap_or("")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    /// One of the 16 standard colours, 0-7 normal and 8-15 bright.
+    Named(u8),
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+impl Serialize for Color {
+    /// Standard colours are serialized by name, everything else as `#rrggbb`.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (r, g, b) = match *self {
+            Color::Named(n) | Color::Indexed(n) if n < 16 => {
+                let name = COLOR_NAMES[(n % 8) as usize];
+                return if n < 8 {
+                    serializer.serialize_str(name)
+                } else {
+                    serializer.serialize_str(&format!("bright_{}", name))
+                };
+            }
+            Color::Named(n) | Color::Indexed(n) if n < 232 => {
+                // 6x6x6 colour cube of the xterm 256-colour palette.
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let n = n - 16;
+                (level(n / 36), level((n / 6) % 6), level(n % 6))
+            }
+            Color::Named(n) | Color::Indexed(n) => {
+                let gray = 8 + (n - 232) * 10;
+                (gray, gray, gray)
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        };
+        serializer.serialize_str(&format!("#{:02x}{:02x}{:02x}", r, g, b))
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Style {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<Color>,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub dim: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub underline: bool,
+}
+
+impl Style {
+    /// Applies the parameters of an SGR (`ESC [ ... m`) sequence.
+    fn apply_sgr(&mut self, params: &str) {
+        let mut codes = params
+            .split([';', ':'])
+            .map(|p| p.parse::<u16>().unwrap_or(0));
+        if params.is_empty() {
+            *self = Style::default();
+            return;
+        }
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Style::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                30..=37 => self.fg = Some(Color::Named((code - 30) as u8)),
+                38 => self.fg = extended_color(&mut codes),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Named((code - 40) as u8)),
+                48 => self.bg = extended_color(&mut codes),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Named((code - 90 + 8) as u8)),
+                100..=107 => self.bg = Some(Color::Named((code - 100 + 8) as u8)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Parses the `5;n` or `2;r;g;b` tail of a `38`/`48` colour code.
+fn extended_color(codes: &mut impl Iterator<Item = u16>) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(Color::Indexed(codes.next()? as u8)),
+        2 => Some(Color::Rgb(
+            codes.next()? as u8,
+            codes.next()? as u8,
+            codes.next()? as u8,
+        )),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Span {
+    pub text: String,
+    #[serde(flatten)]
+    pub style: Style,
+}
+
+/// The furthest column a cursor movement in a log line may jump to. Real terminals
+/// are far narrower; the limit keeps a bogus `ESC[<n>G` from padding a line with
+/// billions of spaces.
+const MAX_COLUMN: usize = 4096;
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    ch: char,
+    style: Style,
+}
+
+/// Plays a line back on a one-line terminal and returns the visible cells.
+fn replay(line: &str, collapse_cr: bool) -> Vec<Cell> {
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut cursor = 0;
+    let mut style = Style::default();
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\x1b' => match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut command = None;
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            command = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    match command {
+                        Some('m') => style.apply_sgr(&params),
+                        // Erase in line: 0 (default) clears to the end, 1 and 2 clear
+                        // up to the cursor or the whole line.
+                        Some('K') => match params.as_str() {
+                            "" | "0" => cells.truncate(cursor),
+                            "2" => cells.clear(),
+                            _ => cells.iter_mut().take(cursor).for_each(|cell| cell.ch = ' '),
+                        },
+                        Some('G') => {
+                            cursor = params
+                                .parse::<usize>()
+                                .unwrap_or(1)
+                                .saturating_sub(1)
+                                .min(MAX_COLUMN - 1);
+                        }
+                        _ => {}
+                    }
+                }
+                // Operating system command, terminated by BEL or ST (`ESC \`).
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\r' if collapse_cr => cursor = 0,
+            '\x08' => cursor = cursor.saturating_sub(1),
+            c if c.is_control() && c != '\t' && c != '\r' => {}
+            c => {
+                // Cursor movement past the end of the line pads with spaces.
+                while cells.len() < cursor {
+                    cells.push(Cell {
+                        ch: ' ',
+                        style: Style::default(),
+                    });
+                }
+                let cell = Cell { ch: c, style };
+                if cursor < cells.len() {
+                    cells[cursor] = cell;
+                } else {
+                    cells.push(cell);
+                }
+                cursor += 1;
+            }
+        }
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red() -> Option<Color> {
+        Some(Color::Named(1))
+    }
+
+    #[test]
+    fn carriage_return_overwrites_line() {
+        assert_eq!(render_plain("Loading  10%\rLoading 100%", true), "Loading 100%");
+        // Without collapsing, `\r` is kept as it is.
+        assert_eq!(render_plain("Loading  10%\rLoading 100%", false), "Loading  10%\rLoading 100%");
+    }
+
+    #[test]
+    fn shorter_overwrite_keeps_rest_of_line() {
+        assert_eq!(render_plain("abcdef\rXY", true), "XYcdef");
+        // Erasing to the end of the line removes what is left.
+        assert_eq!(render_plain("downloading 100%\r\x1b[Kdone", true), "done");
+        assert_eq!(render_plain("abc\x08\x08X", true), "aXc");
+    }
+
+    #[test]
+    fn cursor_movement_is_clamped() {
+        let rendered = render_plain("a\x1b[999999999Gb", true);
+        assert_eq!(rendered.len(), MAX_COLUMN);
+        assert!(rendered.starts_with("a ") && rendered.ends_with(" b"));
+        assert_eq!(normalize("x\x1b[18446744073709551615Gy").len(), MAX_COLUMN);
+        assert_eq!(render_plain("abc\x1b[2GX", true), "aXc");
+    }
+
+    #[test]
+    fn nested_progress_bar_shows_final_state() {
+        // tqdm redraws bars above the current one by moving the cursor up, which a
+        // single line cannot show; the bar's last state is what remains.
+        let line = "\r  0%|          | 0/10\r 50%|#####     | 5/10\x1b[A";
+        assert_eq!(render_plain(line, true), " 50%|#####     | 5/10");
+
+        let keep = RenderOptions {
+            collapse_cr: true,
+            ansi: AnsiMode::Keep,
+        };
+        assert_eq!(keep.render_text(line.to_string()), " 50%|#####     | 5/10\x1b[A");
+        assert_eq!(keep.render_text("step 3\r".to_string()), "step 3");
+    }
+
+    #[test]
+    fn colour_reset_ends_span() {
+        let spans = render_spans("\x1b[31mred\x1b[0m plain \x1b[1;32mok\x1b[39m!\x1b[m.", false);
+        let found: Vec<(&str, Style)> = spans.iter().map(|span| (span.text.as_str(), span.style)).collect();
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            found,
+            vec![
+                ("red", Style { fg: red(), ..Style::default() }),
+                (" plain ", Style::default()),
+                ("ok", Style { fg: Some(Color::Named(2)), ..bold }),
+                ("!", bold),
+                (".", Style::default()),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_escape_sequences() {
+        assert_eq!(render_plain("\x1b[1;31mError:\x1b[0m failed", false), "Error: failed");
+        assert_eq!(render_plain("\x1b]0;title\x07text", false), "text");
+        assert_eq!(
+            normalize("a\r\x1b[32mb\x1b[0m\nc 1%\rc 2%\n"),
+            "b\nc 2%\n"
+        );
+    }
+
+    #[test]
+    fn serializes_colours() {
+        let json = |color: Color| serde_json::to_value(color).unwrap();
+        assert_eq!(json(Color::Named(1)), "red");
+        assert_eq!(json(Color::Named(9)), "bright_red");
+        assert_eq!(json(Color::Indexed(196)), "#ff0000");
+        assert_eq!(json(Color::Indexed(244)), "#808080");
+        assert_eq!(json(Color::Rgb(1, 2, 3)), "#010203");
+    }
+}
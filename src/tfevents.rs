@@ -0,0 +1,409 @@
+use std::{
+    fs::File,
+    io::{BufReader, ErrorKind, Read},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Utc};
+use walkdir::WalkDir;
+
+// --- TensorBoard event files ---
+//
+// Event files are TFRecord files: a sequence of records, each framed as
+//
+//     u64 length | u32 masked crc32c(length) | data[length] | u32 masked crc32c(data)
+//
+// where every record holds a serialized `tensorflow.Event` protobuf. Only the fields
+// needed to read scalar summaries are decoded.
+
+/// Prefix of the files written by TensorBoard summary writers.
+pub const EVENT_FILE_PREFIX: &str = "events.out.tfevents.";
+
+/// How far below the working directory event files are searched for.
+const MAX_SEARCH_DEPTH: usize = 8;
+
+/// A scalar summary value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scalar {
+    pub tag: String,
+    pub step: i64,
+    pub wall_time: f64,
+    pub value: f64,
+}
+
+/// Reads all scalars of an event file. A record that is still being written at the
+/// end of the file, or one that fails its checksum, ends the read.
+pub fn read_scalars(path: &Path) -> std::io::Result<Vec<Scalar>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut scalars = Vec::new();
+    while let Some(record) = read_record(&mut reader)? {
+        scalars.extend(Event::decode(&record).scalars());
+    }
+    Ok(scalars)
+}
+
+fn read_record(reader: &mut impl Read) -> std::io::Result<Option<Vec<u8>>> {
+    let mut header = [0u8; 12];
+    if !read_full(reader, &mut header)? {
+        return Ok(None);
+    }
+    let length_bytes = &header[..8];
+    let length_crc = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if masked_crc32c(length_bytes) != length_crc {
+        return Ok(None);
+    }
+    let length = u64::from_le_bytes(length_bytes.try_into().unwrap()) as usize;
+
+    let mut data = vec![0u8; length];
+    let mut footer = [0u8; 4];
+    if !read_full(reader, &mut data)? || !read_full(reader, &mut footer)? {
+        return Ok(None);
+    }
+    if masked_crc32c(&data) != u32::from_le_bytes(footer) {
+        return Ok(None);
+    }
+    Ok(Some(data))
+}
+
+/// Fills `buffer`, returning false when the file ends first.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<bool> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn masked_crc32c(data: &[u8]) -> u32 {
+    let crc = crc32c(data);
+    (crc.rotate_right(15)).wrapping_add(0xa282_ead8)
+}
+
+fn crc32c(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0x82f6_3b78
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+// --- Protobuf decoding ---
+
+/// A decoded protobuf field value.
+enum FieldValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// Iterates over the fields of a protobuf message. Decoding stops at malformed data.
+struct Fields<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.data.split_first()?;
+            self.data = rest;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() < n {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(n);
+        self.data = rest;
+        Some(taken)
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = (u64, FieldValue<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let key = self.varint()?;
+        let value = match key & 0x7 {
+            0 => FieldValue::Varint(self.varint()?),
+            1 => FieldValue::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().ok()?)),
+            2 => {
+                let len = self.varint()? as usize;
+                FieldValue::Bytes(self.take(len)?)
+            }
+            5 => FieldValue::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().ok()?)),
+            _ => {
+                // Groups are deprecated and never used by event files.
+                self.data = &[];
+                return None;
+            }
+        };
+        Some((key >> 3, value))
+    }
+}
+
+/// The parts of `tensorflow.Event` that hold scalars.
+struct Event<'a> {
+    wall_time: f64,
+    step: i64,
+    /// Serialized `Summary.Value` messages.
+    values: Vec<&'a [u8]>,
+}
+
+impl<'a> Event<'a> {
+    fn decode(data: &'a [u8]) -> Self {
+        let mut event = Event {
+            wall_time: 0.0,
+            step: 0,
+            values: Vec::new(),
+        };
+        for (field, value) in Fields::new(data) {
+            match (field, value) {
+                (1, FieldValue::Fixed64(bits)) => event.wall_time = f64::from_bits(bits),
+                (2, FieldValue::Varint(step)) => event.step = step as i64,
+                // summary: repeated Summary.Value value = 1
+                (5, FieldValue::Bytes(summary)) => {
+                    for (field, value) in Fields::new(summary) {
+                        if let (1, FieldValue::Bytes(value)) = (field, value) {
+                            event.values.push(value);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        event
+    }
+
+    fn scalars(&self) -> impl Iterator<Item = Scalar> + '_ {
+        self.values.iter().filter_map(|value| {
+            let mut tag = None;
+            let mut scalar = None;
+            for (field, value) in Fields::new(value) {
+                match (field, value) {
+                    (1, FieldValue::Bytes(bytes)) => {
+                        tag = Some(String::from_utf8_lossy(bytes).into_owned())
+                    }
+                    // simple_value, written by PyTorch's and TF1's summary writers.
+                    (2, FieldValue::Fixed32(bits)) => scalar = Some(f32::from_bits(bits) as f64),
+                    // tensor, written by TF2's summary writers.
+                    (8, FieldValue::Bytes(tensor)) => scalar = scalar.or(tensor_scalar(tensor)),
+                    _ => {}
+                }
+            }
+            Some(Scalar {
+                tag: tag?,
+                step: self.step,
+                wall_time: self.wall_time,
+                value: scalar?,
+            })
+        })
+    }
+}
+
+/// Reads the value of a single-element float or double `TensorProto`.
+fn tensor_scalar(tensor: &[u8]) -> Option<f64> {
+    const DT_FLOAT: u64 = 1;
+    const DT_DOUBLE: u64 = 2;
+
+    let mut dtype = 0;
+    let mut content = None;
+    let mut value = None;
+    for (field, field_value) in Fields::new(tensor) {
+        match (field, field_value) {
+            (1, FieldValue::Varint(v)) => dtype = v,
+            (4, FieldValue::Bytes(bytes)) => content = Some(bytes),
+            // float_val and double_val, packed or not.
+            (5, FieldValue::Fixed32(bits)) => value = Some(f32::from_bits(bits) as f64),
+            (5, FieldValue::Bytes(bytes)) if bytes.len() >= 4 => {
+                value = Some(f32::from_le_bytes(bytes[..4].try_into().ok()?) as f64)
+            }
+            (6, FieldValue::Fixed64(bits)) => value = Some(f64::from_bits(bits)),
+            (6, FieldValue::Bytes(bytes)) if bytes.len() >= 8 => {
+                value = Some(f64::from_le_bytes(bytes[..8].try_into().ok()?))
+            }
+            _ => {}
+        }
+    }
+    match (dtype, content) {
+        (DT_FLOAT, Some(bytes)) if bytes.len() == 4 => {
+            Some(f32::from_le_bytes(bytes.try_into().ok()?) as f64)
+        }
+        (DT_DOUBLE, Some(bytes)) if bytes.len() == 8 => {
+            Some(f64::from_le_bytes(bytes.try_into().ok()?))
+        }
+        (DT_FLOAT | DT_DOUBLE, _) => value,
+        _ => None,
+    }
+}
+
+// --- Event file discovery ---
+
+/// Returns the creation time encoded in an event file name,
+/// `events.out.tfevents.<unix seconds>.<hostname>...`.
+pub fn event_file_created_at(path: &Path) -> Option<DateTime<Utc>> {
+    let name = path.file_name()?.to_str()?;
+    let timestamp = name.strip_prefix(EVENT_FILE_PREFIX)?.split('.').next()?;
+    DateTime::from_timestamp(timestamp.parse().ok()?, 0)
+}
+
+/// Where to look for a task's event files. IsaacLab's training scripts log below
+/// `logs/`; searching only there keeps the walk away from the rest of the source tree.
+pub fn search_root(working_dir: &Path) -> PathBuf {
+    let logs_dir = working_dir.join("logs");
+    if logs_dir.is_dir() {
+        logs_dir
+    } else {
+        working_dir.to_path_buf()
+    }
+}
+
+/// Finds the event files written by a task: the ones below its working directory that
+/// were created while it ran. Sorted by creation time, so a resumed run follows the
+/// run it continues.
+pub fn find_event_files(
+    search_root: &Path,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+) -> Vec<PathBuf> {
+    // Allow for the clock granularity of the file name and a writer created just
+    // before the task was marked as started.
+    let slack = chrono::Duration::seconds(60);
+    let window_end = finished_at.unwrap_or_else(Utc::now) + slack;
+
+    let mut files: Vec<(DateTime<Utc>, PathBuf)> = WalkDir::new(search_root)
+        .max_depth(MAX_SEARCH_DEPTH)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let created_at = event_file_created_at(entry.path())?;
+            (created_at >= started_at - slack && created_at <= window_end)
+                .then(|| (created_at, entry.into_path()))
+        })
+        .collect();
+    files.sort();
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// The directory of a run: the deepest directory holding all of its event files, so
+/// that a resumed run shows up next to the run it continues.
+pub fn run_dir(event_files: &[PathBuf]) -> Option<PathBuf> {
+    let mut dirs = event_files.iter().filter_map(|path| path.parent());
+    let mut run_dir = dirs.next()?.to_path_buf();
+    for dir in dirs {
+        while !dir.starts_with(&run_dir) {
+            run_dir = run_dir.parent()?.to_path_buf();
+        }
+    }
+    Some(run_dir)
+}
+
+/// Finds the run directory of a task from the event files it wrote below its working
+/// directory.
+pub fn find_run_dir(
+    working_dir: &Path,
+    started_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+) -> Option<PathBuf> {
+    run_dir(&find_event_files(&search_root(working_dir), started_at, finished_at))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    const FIXTURE_NAME: &str = "events.out.tfevents.1767225600.host.1.0";
+    /// Event file with a file version record, `simple_value` scalars as written by
+    /// PyTorch's summary writer, a float and a double tensor as written by TF2's, and
+    /// an image summary. Its records are 40, 83, 55, 62, 61 and 49 bytes long.
+    const FIXTURE: &[u8] = include_bytes!("../tests/fixtures/tfevents/events.out.tfevents.1767225600.host.1.0");
+
+    fn read(bytes: &[u8]) -> Vec<Scalar> {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(bytes).unwrap();
+        read_scalars(file.path()).unwrap()
+    }
+
+    fn scalar(tag: &str, step: i64, wall_time: f64, value: f64) -> Scalar {
+        Scalar {
+            tag: tag.to_string(),
+            step,
+            wall_time,
+            value,
+        }
+    }
+
+    #[test]
+    fn crc32c_matches_known_vectors() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(&[0u8; 32]), 0x8a91_36aa);
+        assert_eq!(crc32c(&[0xffu8; 32]), 0x62a8_ab43);
+        let ascending: Vec<u8> = (0..32).collect();
+        assert_eq!(crc32c(&ascending), 0x46dd_794e);
+    }
+
+    #[test]
+    fn reads_scalar_and_tensor_summaries() {
+        assert_eq!(
+            read(FIXTURE),
+            vec![
+                scalar("Train/mean_reward", 0, 1767225601.5, -0.5),
+                scalar("Loss/value_function", 0, 1767225601.5, 0.25),
+                scalar("Train/mean_reward", 10, 1767225602.5, 1.5),
+                scalar("Train/mean_reward", 20, 1767225603.5, 2.5),
+                scalar("Episode/length", 30, 1767225604.5, 31.25),
+            ]
+        );
+    }
+
+    #[test]
+    fn stops_at_truncated_trailing_record() {
+        let complete = 40 + 83 + 55;
+        // Cut inside the header, the data and the footer of the fourth record.
+        for cut in [complete, complete + 5, complete + 20, complete + 60] {
+            let steps: Vec<i64> = read(&FIXTURE[..cut]).iter().map(|s| s.step).collect();
+            assert_eq!(steps, vec![0, 0, 10], "cut at {}", cut);
+        }
+    }
+
+    #[test]
+    fn stops_at_corrupted_record() {
+        let mut corrupted = FIXTURE.to_vec();
+        // Flip a byte in the data of the third record.
+        corrupted[40 + 83 + 20] ^= 0xff;
+        let steps: Vec<i64> = read(&corrupted).iter().map(|s| s.step).collect();
+        assert_eq!(steps, vec![0, 0]);
+    }
+
+    #[test]
+    fn reads_creation_time_from_file_name() {
+        let created_at = event_file_created_at(Path::new(FIXTURE_NAME)).unwrap();
+        assert_eq!(created_at.timestamp(), 1767225600);
+        assert!(event_file_created_at(Path::new("model_100.pt")).is_none());
+    }
+}